@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+/// A set of patterns, each with a value attached, that can all be found in one pass
+/// over a text, including matches that overlap each other.
+pub struct AhoCorasick<T> {
+    /// The automaton's transitions on each byte, with the failure links already followed.
+    next: Vec<[usize; 256]>,
+    /// The patterns ending at each state, including those reached through failure links.
+    outputs: Vec<Vec<usize>>,
+    lengths: Vec<usize>,
+    values: Vec<T>,
+}
+
+/// One occurrence of a pattern, at the byte offsets `start..end`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Match<'a, T> {
+    pub start: usize,
+    pub end: usize,
+    /// Which pattern matched, counting in the order they were given.
+    pub pattern: usize,
+    pub value: &'a T,
+}
+
+impl<T> AhoCorasick<T> {
+    pub fn new<S: AsRef<str>>(patterns: impl IntoIterator<Item = (S, T)>) -> Self {
+        // state 0 is the root, which is never anyone's child, so 0 can mean "no edge yet"
+        let mut next = vec![[0; 256]];
+        let mut outputs = vec![Vec::new()];
+        let mut lengths = Vec::new();
+        let mut values = Vec::new();
+        for (i, (pattern, value)) in patterns.into_iter().enumerate() {
+            let bytes = pattern.as_ref().as_bytes();
+            assert!(!bytes.is_empty(), "can't search for an empty pattern");
+            let mut state = 0;
+            for &b in bytes {
+                if next[state][b as usize] == 0 {
+                    next.push([0; 256]);
+                    outputs.push(Vec::new());
+                    next[state][b as usize] = next.len() - 1;
+                }
+                state = next[state][b as usize];
+            }
+            outputs[state].push(i);
+            lengths.push(bytes.len());
+            values.push(value);
+        }
+
+        // breadth first, so a state's failure link is always finished before the state
+        let mut fail = vec![0; next.len()];
+        let mut queue = next[0]
+            .iter()
+            .copied()
+            .filter(|&child| child != 0)
+            .collect::<VecDeque<_>>();
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+            let fallback = next[fail[state]];
+            for (child, &other) in next[state].iter_mut().zip(fallback.iter()) {
+                if *child != 0 {
+                    fail[*child] = other;
+                    queue.push_back(*child);
+                } else {
+                    *child = other;
+                }
+            }
+        }
+
+        AhoCorasick {
+            next,
+            outputs,
+            lengths,
+            values,
+        }
+    }
+
+    /// Every occurrence of every pattern in `text`, ordered by where they end.
+    pub fn find_iter<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Match<'a, T>> + 'a {
+        text.bytes()
+            .enumerate()
+            .scan(0, move |state, (i, b)| {
+                *state = self.next[*state][b as usize];
+                Some((i + 1, *state))
+            })
+            .flat_map(move |(end, state)| {
+                self.outputs[state].iter().map(move |&pattern| Match {
+                    start: end - self.lengths[pattern],
+                    end,
+                    pattern,
+                    value: &self.values[pattern],
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_aho_corasick() {
+        let matcher = AhoCorasick::new([("he", 1), ("she", 2), ("his", 3), ("hers", 4)]);
+        let found = matcher
+            .find_iter("ushers")
+            .map(|m| (m.start, m.end, *m.value))
+            .collect::<Vec<_>>();
+        assert_eq!(found, vec![(1, 4, 2), (2, 4, 1), (2, 6, 4)]);
+        assert_eq!(matcher.find_iter("").count(), 0);
+
+        // overlapping occurrences of the same pattern, and patterns that aren't ASCII
+        let matcher = AhoCorasick::new([("aa", 'a'), ("ö", 'o')]);
+        let found = matcher.find_iter("aaaöa").collect::<Vec<_>>();
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[1].start, found[1].end), (1, 3));
+        assert_eq!((found[2].start, found[2].end, found[2].pattern), (3, 5, 1));
+    }
+}
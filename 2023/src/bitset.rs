@@ -0,0 +1,87 @@
+/// A set of integers below a fixed size, one bit each.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// An empty set that can hold `0..size`.
+    pub fn new(size: usize) -> Self {
+        BitSet {
+            words: vec![0; size.div_ceil(64)],
+        }
+    }
+
+    pub fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Add `i`, returning whether it was new.
+    pub fn insert(&mut self, i: usize) -> bool {
+        let new = !self.contains(i);
+        self.words[i / 64] |= 1 << (i % 64);
+        new
+    }
+
+    /// Remove `i`, returning whether it was there.
+    pub fn remove(&mut self, i: usize) -> bool {
+        let present = self.contains(i);
+        self.words[i / 64] &= !(1 << (i % 64));
+        present
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Whether every element of `self` is also in `other`.
+    pub fn is_subset(&self, other: &BitSet) -> bool {
+        self.words
+            .iter()
+            .zip(other.words.iter())
+            .all(|(a, b)| a & !b == 0)
+    }
+
+    /// Add everything in `other` to `self`.
+    pub fn union_with(&mut self, other: &BitSet) {
+        for (a, b) in self.words.iter_mut().zip(other.words.iter()) {
+            *a |= b;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(k, &word)| {
+            (0..64)
+                .filter(move |bit| word & (1 << bit) != 0)
+                .map(move |bit| k * 64 + bit)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_bitset() {
+        let mut set = BitSet::new(130);
+        assert!(set.is_empty());
+        assert!(set.insert(3) && set.insert(64) && set.insert(129));
+        assert!(!set.insert(64));
+        assert!(set.contains(129) && !set.contains(128) && !set.contains(0));
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+        assert!(set.remove(64) && !set.remove(64));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![3, 129]);
+        let mut other = BitSet::new(130);
+        other.insert(3);
+        assert!(other.is_subset(&set) && !set.is_subset(&other));
+        other.insert(70);
+        other.union_with(&set);
+        assert_eq!(other.iter().collect::<Vec<_>>(), vec![3, 70, 129]);
+    }
+}
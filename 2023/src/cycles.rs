@@ -0,0 +1,246 @@
+use num::integer::{ExtendedGcd, Integer};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The sequence `x0, f(x0), f(f(x0)), ...` first repeats after `prefix` steps,
+/// and from then on repeats every `period` steps.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /// The smallest step count whose state equals the state after `n` steps.
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.prefix {
+            n
+        } else {
+            self.prefix + (n - self.prefix) % self.period
+        }
+    }
+}
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "repeats every {} after the first {}",
+            self.period, self.prefix
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// Remember a 64-bit fingerprint of every state seen, confirming any repeat by equality.
+    Hashed,
+    /// Brent's algorithm, which keeps only a couple of states at a time.
+    Brent,
+    /// Floyd's tortoise and hare.
+    Floyd,
+}
+
+fn fingerprint<S: Hash>(state: &S) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    state.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn advance<S, F: FnMut(&S) -> S>(state: &S, step: &mut F, n: usize) -> S
+where
+    S: Clone,
+{
+    let mut state = state.clone();
+    for _ in 0..n {
+        state = step(&state);
+    }
+    state
+}
+
+fn hashed<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> S,
+{
+    let mut seen = HashMap::<u64, usize>::new();
+    let mut state = start.clone();
+    let mut i = 0;
+    loop {
+        if let Some(&j) = seen.get(&fingerprint(&state)) {
+            // guard against fingerprint collisions before trusting the repeat
+            let period = i - j;
+            if advance(&state, &mut step, period) == state {
+                return Cycle { prefix: j, period };
+            }
+        }
+        seen.insert(fingerprint(&state), i);
+        state = step(&state);
+        i += 1;
+    }
+}
+
+fn brent<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let (mut power, mut period) = (1, 1);
+    let mut tortoise = start.clone();
+    let mut hare = step(start);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        hare = step(&hare);
+        period += 1;
+    }
+    let mut tortoise = start.clone();
+    let mut hare = advance(start, &mut step, period);
+    let mut prefix = 0;
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix += 1;
+    }
+    Cycle { prefix, period }
+}
+
+fn floyd<S, F>(start: &S, mut step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    let mut tortoise = step(start);
+    let mut hare = step(&tortoise);
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = advance(&hare, &mut step, 2);
+    }
+    let mut prefix = 0;
+    let mut tortoise = start.clone();
+    while tortoise != hare {
+        tortoise = step(&tortoise);
+        hare = step(&hare);
+        prefix += 1;
+    }
+    let mut period = 1;
+    hare = step(&tortoise);
+    while tortoise != hare {
+        hare = step(&hare);
+        period += 1;
+    }
+    Cycle { prefix, period }
+}
+
+/// Find where repeatedly applying `step` from `start` falls into a cycle.
+/// Only terminates if the sequence does eventually repeat.
+pub fn detect<S, F>(start: &S, step: F, method: Method) -> Cycle
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> S,
+{
+    match method {
+        Method::Hashed => hashed(start, step),
+        Method::Brent => brent(start, step),
+        Method::Floyd => floyd(start, step),
+    }
+}
+
+/// Like `detect` with `Method::Brent`, for states that can't be hashed.
+pub fn detect_unhashed<S, F>(start: &S, step: F) -> Cycle
+where
+    S: Clone + Eq,
+    F: FnMut(&S) -> S,
+{
+    brent(start, step)
+}
+
+/// The state after `n` steps, skipping whole periods once the cycle is found.
+pub fn state_after<S, F>(start: &S, mut step: F, n: usize, method: Method) -> (S, Cycle)
+where
+    S: Clone + Eq + Hash,
+    F: FnMut(&S) -> S,
+{
+    let cycle = detect(start, &mut step, method);
+    (advance(start, &mut step, cycle.reduce(n)), cycle)
+}
+
+/// The smallest non-negative `x` with `x % modulus == residue` for every
+/// `(residue, modulus)` pair, together with the lcm of the moduli,
+/// or `None` if the congruences contradict each other. The moduli must be positive,
+/// but need not be coprime.
+pub fn crt(congruences: &[(i128, i128)]) -> Option<(i128, i128)> {
+    let (mut x, mut n) = (0, 1);
+    for &(r, m) in congruences {
+        assert!(m > 0, "crt needs positive moduli, got {}", m);
+        let ExtendedGcd {
+            gcd, x: inverse, ..
+        } = n.extended_gcd(&m);
+        if (r - x) % gcd != 0 {
+            return None;
+        }
+        // x + n t satisfies both once t = (r - x) / gcd * inverse (mod m / gcd)
+        let step = m / gcd;
+        let t = ((r - x) / gcd % step * (inverse % step)).rem_euclid(step);
+        let lcm = n * step;
+        x = (x + n * t).rem_euclid(lcm);
+        n = lcm;
+    }
+    Some((x, n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_cycle_methods_agree() {
+        // x -> x*x + 1 (mod 1000) from 2 has a tail before it starts repeating
+        let step = |&x: &u64| (x * x + 1) % 1000;
+        let expected = {
+            let mut seen = Vec::new();
+            let mut x = 2;
+            while !seen.contains(&x) {
+                seen.push(x);
+                x = step(&x);
+            }
+            let prefix = seen.iter().position(|&y| y == x).unwrap();
+            Cycle {
+                prefix,
+                period: seen.len() - prefix,
+            }
+        };
+        assert!(expected.prefix > 0 && expected.period > 1);
+        for method in [Method::Hashed, Method::Brent, Method::Floyd] {
+            assert_eq!(detect(&2, step, method), expected);
+        }
+        assert_eq!(detect_unhashed(&2, step), expected);
+    }
+
+    #[test]
+    fn check_state_after() {
+        let step = |&x: &u64| (x * x + 1) % 1000;
+        let direct = (0..12345).fold(2, |x, _| step(&x));
+        for method in [Method::Hashed, Method::Brent, Method::Floyd] {
+            assert_eq!(state_after(&2, step, 12345, method).0, direct);
+            assert_eq!(state_after(&2, step, 3, method).0, 677);
+        }
+    }
+
+    #[test]
+    fn check_crt() {
+        assert_eq!(crt(&[(2, 3), (3, 5), (2, 7)]), Some((23, 105)));
+        assert_eq!(crt(&[(1, 4), (3, 6)]), Some((9, 12)));
+        assert_eq!(crt(&[(1, 4), (2, 6)]), None);
+        assert_eq!(crt(&[(0, 3823), (0, 3847)]), Some((0, 3823 * 3847)));
+        assert_eq!(crt(&[]), Some((0, 1)));
+        for modulus in [0, -3] {
+            assert!(std::panic::catch_unwind(|| crt(&[(1, 4), (0, modulus)])).is_err());
+        }
+    }
+}
@@ -1,50 +1,48 @@
+use crate::aho_corasick::AhoCorasick;
 use crate::Solution;
+use std::cmp::Reverse;
 
 pub struct Day01;
 
-static TOKENS: [(&'static str, u32); 20] = [
-    ("0", 0),
-    ("1", 1),
-    ("2", 2),
-    ("3", 3),
-    ("4", 4),
-    ("5", 5),
-    ("6", 6),
-    ("7", 7),
-    ("8", 8),
-    ("9", 9),
-    ("zero", 0),
-    ("one", 1),
-    ("two", 2),
-    ("three", 3),
-    ("four", 4),
-    ("five", 5),
-    ("six", 6),
-    ("seven", 7),
-    ("eight", 8),
-    ("nine", 9),
+/// Number words in a few languages, from zero to nine.
+pub const ENGLISH: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+pub const GERMAN: [&str; 10] = [
+    "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+];
+pub const FRENCH: [&str; 10] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
 ];
 
 impl Day01 {
-    fn find_digit(line: &str, reverse: bool) -> u32 {
-        let mut result = None; // (index, digit) option
-        for (token, digit) in TOKENS.iter() {
-            let find_result = if reverse {
-                line.rfind(token)
-            } else {
-                line.find(token)
-            };
-            if let Some(i) = find_result {
-                if result.is_none() {
-                    result = Some((i, digit));
-                } else if let Some((j, _)) = result {
-                    if (reverse && i > j) || (!reverse && i < j) {
-                        result = Some((i, digit));
-                    }
-                }
-            }
-        }
-        *result.unwrap().1
+    /// A scanner for the digits `0` to `9` and each list of number words,
+    /// where the word at index `i` stands for `i`.
+    pub fn scanner(vocabularies: &[&[&str]]) -> AhoCorasick<u32> {
+        let digits = (0..10).map(|d| (d.to_string(), d));
+        let words = vocabularies
+            .iter()
+            .flat_map(|words| words.iter().zip(0..).map(|(w, d)| (w.to_string(), d)));
+        AhoCorasick::new(digits.chain(words))
+    }
+
+    /// The first and last tokens on the line combined into a two-digit number.
+    /// Where tokens start at the same place the longest one counts.
+    pub fn calibration_value(scanner: &AhoCorasick<u32>, line: &str) -> Option<u32> {
+        let matches = scanner.find_iter(line).collect::<Vec<_>>();
+        let first = matches.iter().min_by_key(|m| (m.start, Reverse(m.end)))?;
+        let last = matches.iter().max_by_key(|m| (m.start, m.end))?;
+        Some(first.value * 10 + last.value)
+    }
+
+    fn calibration_sum(scanner: &AhoCorasick<u32>, input: &str) -> u32 {
+        input
+            .lines()
+            .map(|line| {
+                Self::calibration_value(scanner, line)
+                    .unwrap_or_else(|| panic!("Day 1: no digits in {:?}", line))
+            })
+            .sum()
     }
 }
 
@@ -56,25 +54,11 @@ impl Solution for Day01 {
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
-        let mut calibration_values = vec![];
-        for line in _parsed_input.lines() {
-            let first_digit = line.chars().find_map(|c| c.to_digit(10)).unwrap();
-            let last_digit = line.chars().rev().find_map(|c| c.to_digit(10)).unwrap();
-            let calibration_value = first_digit * 10 + last_digit;
-            calibration_values.push(calibration_value);
-        }
-        calibration_values.iter().sum::<u32>().to_string()
+        Self::calibration_sum(&Self::scanner(&[]), _parsed_input).to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
-        let mut calibration_values = vec![];
-        for line in _parsed_input.lines() {
-            let first_digit = Self::find_digit(line, false);
-            let last_digit = Self::find_digit(line, true);
-            let calibration_value = first_digit * 10 + last_digit;
-            calibration_values.push(calibration_value);
-        }
-        calibration_values.iter().sum::<u32>().to_string()
+        Self::calibration_sum(&Self::scanner(&[&ENGLISH]), _parsed_input).to_string()
     }
 }
 
@@ -125,4 +109,25 @@ zoneight234
             "281".to_string()
         )
     }
+
+    #[test]
+    fn check_day01_vocabularies() {
+        let scanner = Day01::scanner(&[&ENGLISH]);
+        let tokens = scanner
+            .find_iter("xtwone3four")
+            .map(|m| (m.start, *m.value))
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, vec![(1, 2), (3, 1), (6, 3), (7, 4)]);
+        assert_eq!(Day01::calibration_value(&scanner, "eighthree"), Some(83));
+        assert_eq!(Day01::calibration_value(&scanner, "abc"), None);
+
+        let scanner = Day01::scanner(&[&GERMAN, &FRENCH]);
+        assert_eq!(
+            Day01::calibration_value(&scanner, "zweiundvierzig"),
+            Some(24)
+        );
+        assert_eq!(Day01::calibration_value(&scanner, "fünfxhuit"), Some(58));
+        // words from different languages can overlap, like the French "un" ending "neun"
+        assert_eq!(Day01::calibration_value(&scanner, "neun"), Some(91));
+    }
 }
@@ -1,116 +1,197 @@
+use crate::intervals::{Interval, IntervalSet, OffsetMap};
+use crate::parse::{self, ParseError, Span};
 use crate::Solution;
 use itertools::Itertools;
-use std::cmp;
+use std::fmt;
 
 pub struct Day05;
 
-type Range = (u32, u32);
-
 pub struct Almanac {
-    seeds: Vec<u32>,
-    seed_ranges: Vec<Range>,
-    maps: Vec<Vec<(u32, u32, u32)>>,
+    seeds: Vec<u64>,
+    seed_ranges: IntervalSet<u64>,
+    /// The name of each map, like `seed-to-soil`.
+    names: Vec<String>,
+    maps: Vec<OffsetMap<u64>>,
+}
+
+/// Things about one of the almanac's maps that the puzzle doesn't rule out,
+/// but that are worth knowing before trusting its answers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapCheck {
+    pub name: String,
+    /// Pairs of lines (counting from 0) whose source ranges overlap, and where.
+    /// The earlier line wins there.
+    pub overlaps: Vec<(usize, usize, Interval<u64>)>,
+    /// Stretches between the lowest and highest source that no line covers,
+    /// which are left unchanged.
+    pub gaps: IntervalSet<u64>,
+    /// Numbers that more than one number maps to, so going backwards from them
+    /// gives several answers.
+    pub collisions: IntervalSet<u64>,
+}
+
+impl fmt::Display for MapCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:", self.name)?;
+        if self.overlaps.is_empty() && self.gaps.is_empty() && self.collisions.is_empty() {
+            return write!(f, " ok");
+        }
+        for (i, j, overlap) in self.overlaps.iter() {
+            write!(
+                f,
+                " lines {} and {} overlap on {}..={};",
+                i, j, overlap.lo, overlap.hi
+            )?;
+        }
+        for gap in self.gaps.iter() {
+            write!(f, " {}..={} is unmapped;", gap.lo, gap.hi)?;
+        }
+        for collision in self.collisions.iter() {
+            write!(
+                f,
+                " {}..={} is reached more than once;",
+                collision.lo, collision.hi
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl Day05 {
-    fn apply_map(map: &Vec<(u32, u32, u32)>, range: Range) -> Vec<Range> {
-        let (mut start, mut seeds) = range;
-        let mut i = match map.binary_search_by_key(&start, |t| t.1) {
-            Ok(i) => i,
-            Err(i) => i.saturating_sub(1),
+    fn parse_almanac(input: Span) -> Result<Almanac, ParseError> {
+        let blocks = input.blocks();
+        let Some(&first) = blocks.first() else {
+            return Err(input.error("expected a seeds line"));
         };
-        let mut result = Vec::new();
-        loop {
-            if i >= map.len() {
-                result.push((start, seeds));
-                break;
-            }
-            let (dst, src, len) = map[i];
-            let mapped_seeds;
-            if start < src {
-                mapped_seeds = cmp::min(src - start, seeds);
-                result.push((start, mapped_seeds));
-            } else if start - src < len {
-                mapped_seeds = cmp::min(len - (start - src), seeds);
-                result.push(((start - src) + dst, mapped_seeds));
-                i += 1;
-            } else {
-                // assume non-overlapping ranges
-                result.push((start, seeds));
-                break;
-            }
-            if seeds == mapped_seeds {
-                break;
+        let (_, seeds) = parse::key_values::<u64>(first, ":")?;
+        if seeds.len() % 2 != 0 {
+            return Err(first.error("seeds come in pairs of a start and a length"));
+        }
+        let mut seed_ranges = Vec::new();
+        for (&start, &len) in seeds.iter().tuples() {
+            if start.checked_add(len.saturating_sub(1)).is_none() {
+                return Err(first.error("range goes past the largest u64"));
             }
-            start += mapped_seeds;
-            seeds -= mapped_seeds;
+            seed_ranges.extend(Interval::from_start_len(start, len));
+        }
+        let mut names = Vec::new();
+        let mut maps = Vec::new();
+        for block in blocks[1..].iter() {
+            let (header, ranges) = block.split_once("\n")?;
+            names.push(header.trim().strip_suffix(" map:")?.text.to_string());
+            let map = parse::lines(ranges, |line| {
+                let [dst, src, len] = parse::tuple::<u64, 3>(line, " ")?;
+                let last = len.saturating_sub(1);
+                if src.checked_add(last).is_none() || dst.checked_add(last).is_none() {
+                    return Err(line.error("range goes past the largest u64"));
+                }
+                Ok(Interval::from_start_len(src, len).map(|source| (source, dst)))
+            })?;
+            maps.push(map.into_iter().flatten().collect());
         }
-        result
+        Ok(Almanac {
+            seeds,
+            seed_ranges: seed_ranges.into_iter().collect(),
+            names,
+            maps,
+        })
     }
 
-    fn apply_maps(range: Range, maps: &Vec<Vec<(u32, u32, u32)>>) -> Vec<Range> {
-        maps.iter()
-            .fold(Vec::from([range]), |ranges: Vec<Range>, map| {
-                ranges
+    pub fn check_maps(almanac: &Almanac) -> Vec<MapCheck> {
+        almanac
+            .names
+            .iter()
+            .zip(almanac.maps.iter())
+            .map(|(name, map)| {
+                let pieces = map.pieces();
+                let mut overlaps = Vec::new();
+                for (i, (a, _)) in pieces.iter().enumerate() {
+                    for (j, (b, _)) in pieces.iter().enumerate().skip(i + 1) {
+                        if let Some(overlap) = a.intersect(b) {
+                            overlaps.push((i, j, overlap));
+                        }
+                    }
+                }
+                let sources = pieces
                     .iter()
-                    .map(|&r| Self::apply_map(map, r))
-                    .flatten()
-                    .collect()
+                    .map(|&(source, _)| source)
+                    .collect::<IntervalSet<_>>();
+                let gaps = match (sources.min(), sources.max()) {
+                    (Some(lo), Some(hi)) => {
+                        IntervalSet::from_iter([Interval::new(lo, hi)]).difference(&sources)
+                    }
+                    _ => IntervalSet::new(),
+                };
+                // where the images of the pieces that actually apply overlap each other
+                let mut images = map
+                    .normalized()
+                    .into_iter()
+                    .map(|(source, dst)| source.moved_to(dst))
+                    .collect::<Vec<_>>();
+                images.sort();
+                let mut reached: Option<u64> = None;
+                let mut collisions = Vec::new();
+                for image in images {
+                    if let Some(hi) = reached.filter(|&hi| hi >= image.lo) {
+                        collisions.push(Interval::new(image.lo, hi.min(image.hi)));
+                    }
+                    reached = Some(reached.map_or(image.hi, |hi| hi.max(image.hi)));
+                }
+                MapCheck {
+                    name: name.clone(),
+                    overlaps,
+                    gaps,
+                    collisions: collisions.into_iter().collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// All the maps composed into one, from seed straight to location.
+    pub fn pipeline(almanac: &Almanac) -> OffsetMap<u64> {
+        almanac
+            .maps
+            .iter()
+            .fold(OffsetMap::from_iter([]), |pipeline, map| {
+                pipeline.compose(map)
             })
     }
+
+    /// Every seed (planted or not) that ends up at one of `locations`.
+    pub fn seeds_for(almanac: &Almanac, locations: &IntervalSet<u64>) -> IntervalSet<u64> {
+        almanac
+            .maps
+            .iter()
+            .rev()
+            .fold(locations.clone(), |set, map| map.preimage(&set))
+    }
 }
 
 impl Solution for Day05 {
     type ParsedInput = Almanac;
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        let (line, lines) = input_lines.split_once("\n\n").unwrap();
-        let seeds: Vec<u32> = line
-            .split_once(": ")
-            .unwrap()
-            .1
-            .split_whitespace()
-            .map(|s| s.parse::<u32>().unwrap())
-            .collect();
-        let seed_ranges: Vec<Range> = seeds.clone().into_iter().tuples().collect();
-        let mut maps = Vec::new();
-        for p in lines.split("\n\n").map(|p| p.split_once("\n").unwrap().1) {
-            let mut map = Vec::new();
-            for l in p.lines() {
-                let (dst, src, len) = l
-                    .split_whitespace()
-                    .map(|s| s.parse::<u32>().unwrap())
-                    .collect_tuple()
-                    .unwrap();
-                map.push((dst, src, len));
-            }
-            map.sort_by_key(|t| t.1); // sort for binary search
-            maps.push(map);
-        }
-        Almanac {
-            seeds: seeds,
-            seed_ranges: seed_ranges,
-            maps: maps,
-        }
+        Self::parse_almanac(Span::new(input_lines)).unwrap_or_else(|e| panic!("Day 5: {}", e))
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
         let almanac = _parsed_input;
-        let apply = |&seed| Self::apply_maps((seed, 1), &almanac.maps)[0].0;
-        almanac.seeds.iter().map(apply).min().unwrap().to_string()
+        let pipeline = Self::pipeline(almanac);
+        almanac
+            .seeds
+            .iter()
+            .map(|&seed| pipeline.map_point(seed))
+            .min()
+            .unwrap()
+            .to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let almanac = _parsed_input;
-        let apply = |&r| Self::apply_maps(r, &almanac.maps);
-        almanac
-            .seed_ranges
-            .iter()
-            .map(apply)
-            .flatten()
+        Self::pipeline(almanac)
+            .map_set(&almanac.seed_ranges)
             .min()
             .unwrap()
-            .0
             .to_string()
     }
 }
@@ -162,4 +243,85 @@ humidity-to-location map:
     fn check_day05_part2_case1() {
         assert_eq!(Day05::solve_part_two(TEST_INPUT), "46".to_string())
     }
+
+    #[test]
+    fn check_day05_parse_error() {
+        let input = TEST_INPUT.replace("52 50 48", "52 50 4B");
+        let error = Day05::parse_almanac(Span::new(&input)).err().unwrap();
+        assert_eq!((error.line, error.column), (5, 7));
+        for input in ["", "\n\n"] {
+            let error = Day05::parse_almanac(Span::new(input)).err().unwrap();
+            assert_eq!(error.message, "expected a seeds line");
+        }
+    }
+
+    #[test]
+    fn check_day05_pipeline() {
+        let almanac = Day05::parse_input(TEST_INPUT);
+        let pipeline = Day05::pipeline(&almanac);
+        for (seed, location) in [(79, 82), (14, 43), (55, 86), (13, 35), (82, 46)] {
+            assert_eq!(pipeline.map_point(seed), location);
+        }
+        assert!(pipeline.to_string().lines().count() > 7);
+        let seeds = Day05::seeds_for(&almanac, &IntervalSet::from_iter([Interval::new(46, 46)]));
+        assert!(seeds.contains(82));
+        assert!(seeds
+            .iter()
+            .all(|i| (i.lo..=i.hi).all(|s| pipeline.map_point(s) == 46)));
+        let planted = seeds.intersection(&almanac.seed_ranges);
+        assert_eq!(
+            planted.iter().collect::<Vec<_>>(),
+            vec![&Interval::new(82, 82)]
+        );
+        assert!(Day05::check_maps(&almanac)
+            .iter()
+            .all(|check| check.overlaps.is_empty() && check.collisions.is_empty()));
+    }
+
+    #[test]
+    fn check_day05_large_and_overlapping() {
+        let input = "seeds: 5000000000 10 40 20
+
+a-to-b map:
+9000000000 5000000000 5
+0 4999999998 4
+100 45 20
+
+b-to-c map:
+0 0 18446744073709551615";
+        let almanac = Day05::parse_input(input);
+        assert_eq!(Day05::solve_part_one(input), "10");
+        assert_eq!(Day05::solve_part_two(input), "40");
+        let checks = Day05::check_maps(&almanac);
+        assert_eq!(
+            checks[0].overlaps,
+            vec![(0, 1, Interval::new(5000000000, 5000000001))]
+        );
+        assert_eq!(
+            checks[0].to_string(),
+            "a-to-b: lines 0 and 1 overlap on 5000000000..=5000000001; \
+             65..=4999999997 is unmapped; 0..=1 is reached more than once; \
+             100..=119 is reached more than once; \
+             9000000000..=9000000004 is reached more than once;"
+        );
+        assert_eq!(checks[1].to_string(), "b-to-c: ok");
+
+        let input = input.replace("0 0 18446744073709551615", "2 0 18446744073709551615");
+        let error = Day05::parse_almanac(Span::new(&input)).err().unwrap();
+        assert_eq!((error.line, error.column), (9, 1));
+
+        for (seeds, message) in [
+            ("18446744073709551615 2", "range goes past the largest u64"),
+            ("79 14 55", "seeds come in pairs of a start and a length"),
+        ] {
+            let input = input.replace("5000000000 10 40 20", seeds);
+            let error = Day05::parse_almanac(Span::new(&input)).err().unwrap();
+            assert_eq!((error.line, error.column), (1, 1));
+            assert_eq!(error.message, message);
+        }
+        let input = input
+            .replace("5000000000 10 40 20", "18446744073709551615 1")
+            .replace("2 0 18446744073709551615", "0 0 18446744073709551615");
+        assert!(Day05::parse_almanac(Span::new(&input)).is_ok());
+    }
 }
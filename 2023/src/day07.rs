@@ -1,172 +1,241 @@
 use crate::Solution;
 use itertools::Itertools;
+use std::cmp::Ordering;
+use std::fmt;
 
 pub struct Day07;
 
-#[derive(Copy, Clone, Eq, Ord, PartialEq, PartialOrd)]
-pub enum Label {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
+/// How hands of the same type are told apart.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Compare the cards one by one in the order they were dealt.
+    InOrder,
+    /// Compare the cards one by one from the strongest down, whatever the order they were dealt.
+    Sorted,
 }
 
-#[derive(Eq, Ord, PartialEq, PartialOrd)]
-pub enum HandType {
-    HighCard,
-    OnePair,
-    TwoPair,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
+/// The rules of a game of Camel Cards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    /// Every card label, weakest first.
+    pub order: Vec<char>,
+    /// Labels that join whichever group of cards makes the hand strongest.
+    /// They still break ties by their place in `order`.
+    pub wild: Vec<char>,
+    pub hand_size: usize,
+    pub tie_break: TieBreak,
 }
 
-#[derive(Eq, Ord, PartialEq, PartialOrd)]
-pub struct Hand {
-    hand_type: HandType,
-    cards: (Label, Label, Label, Label, Label),
-    bid: usize,
-}
+impl Rules {
+    pub fn standard() -> Self {
+        Rules {
+            order: "23456789TJQKA".chars().collect(),
+            wild: Vec::new(),
+            hand_size: 5,
+            tie_break: TieBreak::InOrder,
+        }
+    }
 
-impl Day07 {
-    fn char_to_label(c: char) -> Label {
-        match c {
-            'A' => Label::Ace,
-            'K' => Label::King,
-            'Q' => Label::Queen,
-            'J' => Label::Jack,
-            'T' => Label::Ten,
-            '9' => Label::Nine,
-            '8' => Label::Eight,
-            '7' => Label::Seven,
-            '6' => Label::Six,
-            '5' => Label::Five,
-            '4' => Label::Four,
-            '3' => Label::Three,
-            '2' => Label::Two,
-            _ => panic!("Day 7: Invalid card label"),
+    /// Jacks become jokers: wild, and the weakest card on their own.
+    pub fn jokers() -> Self {
+        Rules {
+            order: "J23456789TQKA".chars().collect(),
+            wild: vec!['J'],
+            ..Self::standard()
         }
     }
 
-    fn count_cards(cards: &Vec<Label>) -> Vec<usize> {
-        cards
+    fn strength(&self, card: char) -> usize {
+        self.order
             .iter()
-            .sorted()
-            .group_by(|&card| *card)
+            .position(|&c| c == card)
+            .unwrap_or_else(|| panic!("Day 7: Invalid card label {:?}", card))
+    }
+
+    /// The sizes of the groups of equal cards, largest first, with the wild cards added
+    /// to the largest group. Hand types compare the same way these do.
+    pub fn hand_type(&self, cards: &str) -> Vec<usize> {
+        let (wild, rest): (Vec<char>, Vec<char>) =
+            cards.chars().partition(|c| self.wild.contains(c));
+        let mut counts = rest
             .into_iter()
-            .map(|(_, group)| group.count())
+            .counts()
+            .into_values()
             .sorted()
             .rev()
-            .collect::<Vec<usize>>()
-    }
-
-    fn count_cards_with_joker(cards: &Vec<Label>) -> Vec<usize> {
-        // count jokers and other cards
-        let joker_count = cards.iter().filter(|&c| *c == Label::Joker).count();
-        let mut counts = Self::count_cards(
-            &cards
-                .clone()
-                .into_iter()
-                .filter(|&card| card != Label::Joker)
-                .collect(),
-        );
-        // apportion jokers appropriately
-        if counts.len() == 0 {
-            counts.push(joker_count);
-        } else {
-            counts[0] += joker_count;
+            .collect::<Vec<usize>>();
+        match counts.first_mut() {
+            Some(largest) => *largest += wild.len(),
+            None => counts.push(wild.len()),
         }
         counts
     }
 
-    fn counts_to_hand_type(counts: &Vec<usize>) -> HandType {
-        // where counts is sorted in descending order
-        match counts[0] {
-            5 => HandType::FiveOfAKind,
-            4 => HandType::FourOfAKind,
-            3 => match counts[1] {
-                2 => HandType::FullHouse,
-                _ => HandType::ThreeOfAKind,
-            },
-            2 => match counts[1] {
-                2 => HandType::TwoPair,
-                _ => HandType::OnePair,
-            },
-            _ => HandType::HighCard,
+    /// The card strengths compared, in order, between hands of the same type.
+    fn tie_break_strengths(&self, cards: &str) -> Vec<usize> {
+        let strengths = cards.chars().map(|c| self.strength(c));
+        match self.tie_break {
+            TieBreak::InOrder => strengths.collect(),
+            TieBreak::Sorted => strengths.sorted().rev().collect(),
         }
     }
 
-    fn parse_hand(line: &str) -> (Hand, Hand) {
-        let (cards_str, bid_str) = line.split_once(" ").unwrap();
-        let bid = bid_str.parse::<usize>().unwrap();
-        let cards: Vec<Label> = cards_str.chars().map(Self::char_to_label).collect();
-        let hand_type = {
-            let counts = Self::count_cards(&cards);
-            Self::counts_to_hand_type(&counts)
-        };
-        let cards_with_joker: Vec<Label> = cards
-            .iter()
-            .map(|&c| match c {
-                Label::Jack => Label::Joker,
-                _ => c,
-            })
-            .collect();
-        let hand_type_with_joker = {
-            let counts = Self::count_cards_with_joker(&cards_with_joker);
-            Self::counts_to_hand_type(&counts)
+    /// What hands are ranked by: their type, then the card strengths in tie-break order.
+    fn key(&self, cards: &str) -> (Vec<usize>, Vec<usize>) {
+        assert_eq!(
+            cards.chars().count(),
+            self.hand_size,
+            "Day 7: {:?} is not a hand of {} cards",
+            cards,
+            self.hand_size
+        );
+        (self.hand_type(cards), self.tie_break_strengths(cards))
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.key(a).cmp(&self.key(b))
+    }
+
+    /// Compare two hands and say what decided it.
+    pub fn explain(&self, a: &str, b: &str) -> Explanation {
+        let (type_a, strengths_a) = self.key(a);
+        let (type_b, strengths_b) = self.key(b);
+        let reason = if type_a != type_b {
+            Reason::HandType(type_name(&type_a), type_name(&type_b))
+        } else {
+            match (0..self.hand_size).find(|&i| strengths_a[i] != strengths_b[i]) {
+                Some(i) => Reason::Card {
+                    hand_type: type_name(&type_a),
+                    position: i + 1,
+                    cards: (self.order[strengths_a[i]], self.order[strengths_b[i]]),
+                },
+                None => Reason::Tie(type_name(&type_a)),
+            }
         };
-        (
-            Hand {
-                hand_type: hand_type,
-                cards: cards.into_iter().collect_tuple().unwrap(),
-                bid: bid,
-            },
-            Hand {
-                hand_type: hand_type_with_joker,
-                cards: cards_with_joker.into_iter().collect_tuple().unwrap(),
-                bid: bid,
-            },
-        )
-    }
-
-    fn total_winnings(hands: &Vec<Hand>) -> usize {
-        // assumes hands is sorted with ascending rank
+        Explanation {
+            hands: (a.to_string(), b.to_string()),
+            ordering: (type_a, strengths_a).cmp(&(type_b, strengths_b)),
+            reason,
+        }
+    }
+
+    /// The total winnings of the hands, ranked weakest first.
+    pub fn total_winnings(&self, hands: &[Hand]) -> usize {
         hands
             .iter()
+            .sorted_by_cached_key(|h| self.key(&h.cards))
             .enumerate()
             .map(|(r, h)| (r + 1) * h.bid)
             .sum::<usize>()
     }
 }
 
+/// The usual name for a hand type, given its group sizes.
+fn type_name(counts: &[usize]) -> String {
+    match counts {
+        [5] => "five of a kind".to_string(),
+        [4, 1] => "four of a kind".to_string(),
+        [3, 2] => "a full house".to_string(),
+        [3, 1, 1] => "three of a kind".to_string(),
+        [2, 2, 1] => "two pair".to_string(),
+        [2, 1, 1, 1] => "one pair".to_string(),
+        [1, 1, 1, 1, 1] => "high card".to_string(),
+        _ => format!("groups of {}", counts.iter().join("+")),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Reason {
+    /// The hands are of different types, named for the first hand and then the second.
+    HandType(String, String),
+    /// The hands are of the same type, and the first differing card (counting from 1)
+    /// after applying the tie-break rule decided it.
+    Card {
+        hand_type: String,
+        position: usize,
+        cards: (char, char),
+    },
+    /// The hands are of the same type and no card tells them apart.
+    Tie(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Explanation {
+    pub hands: (String, String),
+    /// How the first hand compares to the second.
+    pub ordering: Ordering,
+    pub reason: Reason,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (a, b) = &self.hands;
+        let (winner, loser) = match self.ordering {
+            Ordering::Less => (b, a),
+            _ => (a, b),
+        };
+        match &self.reason {
+            Reason::Tie(hand_type) => write!(f, "{} ties with {}: both are {}", a, b, hand_type),
+            Reason::HandType(type_a, type_b) => {
+                let (winning, losing) = match self.ordering {
+                    Ordering::Less => (type_b, type_a),
+                    _ => (type_a, type_b),
+                };
+                write!(
+                    f,
+                    "{} beats {}: {} beats {}",
+                    winner, loser, winning, losing
+                )
+            }
+            Reason::Card {
+                hand_type,
+                position,
+                cards,
+            } => {
+                let (winning, losing) = match self.ordering {
+                    Ordering::Less => (cards.1, cards.0),
+                    _ => (cards.0, cards.1),
+                };
+                write!(
+                    f,
+                    "{} beats {}: both are {}, and card {} is {} against {}",
+                    winner, loser, hand_type, position, winning, losing
+                )
+            }
+        }
+    }
+}
+
+pub struct Hand {
+    cards: String,
+    bid: usize,
+}
+
+impl Day07 {
+    fn parse_hand(line: &str) -> Hand {
+        let (cards_str, bid_str) = line.split_once(" ").unwrap();
+        Hand {
+            cards: cards_str.to_string(),
+            bid: bid_str.parse::<usize>().unwrap(),
+        }
+    }
+}
+
 impl Solution for Day07 {
-    type ParsedInput = (Vec<Hand>, Vec<Hand>);
+    type ParsedInput = Vec<Hand>;
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        input_lines.lines().map(Self::parse_hand).unzip()
+        input_lines.lines().map(Self::parse_hand).collect()
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
-        let hands = &mut _parsed_input.0;
-        hands.sort();
-        Self::total_winnings(&hands).to_string()
+        let hands = _parsed_input;
+        Rules::standard().total_winnings(hands).to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
-        let hands = &mut _parsed_input.1;
-        hands.sort();
-        Self::total_winnings(&hands).to_string()
+        let hands = _parsed_input;
+        Rules::jokers().total_winnings(hands).to_string()
     }
 }
 
@@ -189,4 +258,44 @@ QQQJA 483";
     fn check_day07_part2_case1() {
         assert_eq!(Day07::solve_part_two(TEST_INPUT), "5905".to_string())
     }
+
+    #[test]
+    fn check_day07_explain() {
+        let standard = Rules::standard();
+        assert_eq!(
+            standard.explain("KTJJT", "KK677").to_string(),
+            "KK677 beats KTJJT: both are two pair, and card 2 is K against T"
+        );
+        assert_eq!(
+            Rules::jokers().explain("KTJJT", "KK677").to_string(),
+            "KTJJT beats KK677: four of a kind beats two pair"
+        );
+        assert_eq!(
+            Rules::jokers().explain("JKKK2", "QQQQ2").to_string(),
+            "QQQQ2 beats JKKK2: both are four of a kind, and card 1 is Q against J"
+        );
+        assert_eq!(standard.compare("T55J5", "T55J5"), Ordering::Equal);
+        assert_eq!(Rules::jokers().hand_type("JJJJJ"), vec![5]);
+    }
+
+    #[test]
+    fn check_day07_custom_rules() {
+        // three-card hands of one suit, where 2s are wild and ties go by the best card
+        let rules = Rules {
+            order: "23456789TJQKA".chars().collect(),
+            wild: vec!['2'],
+            hand_size: 3,
+            tie_break: TieBreak::Sorted,
+        };
+        assert_eq!(rules.hand_type("2A2"), vec![3]);
+        assert_eq!(rules.compare("A34", "3K4"), Ordering::Greater);
+        let explanation = rules.explain("KK3", "Q2Q");
+        assert_eq!(explanation.ordering, Ordering::Less);
+        assert_eq!(
+            explanation.to_string(),
+            "Q2Q beats KK3: groups of 3 beats groups of 2+1"
+        );
+        let hands = Day07::parse_input("KK3 1\nQ2Q 10\nA34 100");
+        assert_eq!(rules.total_winnings(&hands), 100 + 2 + 30);
+    }
 }
@@ -1,8 +1,10 @@
+use crate::cycles::{self, Cycle, Method};
 use crate::Solution;
 use itertools::Itertools;
 use num::integer::lcm;
 use regex::Regex;
 use std::collections::HashMap;
+use std::fmt::Write;
 
 pub struct Day08;
 
@@ -40,6 +42,66 @@ impl Day08 {
         }
         (node.to_string(), steps)
     }
+
+    /// Detect where a ghost starting at `src` starts walking in circles,
+    /// and the steps (before the sequence first repeats) at which it is on a destination.
+    fn ghost_cycle(
+        directions: &[Direction],
+        network: &HashMap<Node, (Node, Node)>,
+        src: Node,
+        at_dst: &dyn Fn(&Node) -> bool,
+    ) -> (Cycle, Vec<usize>) {
+        let step = |(node, i): &(Node, usize)| {
+            let next = match directions[*i] {
+                Direction::Left => &network[node].0,
+                Direction::Right => &network[node].1,
+            };
+            (next.to_string(), (i + 1) % directions.len())
+        };
+        let start = (src, 0);
+        let cycle = cycles::detect(&start, step, Method::Hashed);
+        let hits = (0..cycle.prefix + cycle.period)
+            .scan(start, |state, i| {
+                let hit = at_dst(&state.0).then_some(i);
+                *state = step(state);
+                Some(hit)
+            })
+            .flatten()
+            .collect();
+        (cycle, hits)
+    }
+
+    /// The cycle of every ghost starting on a node ending in `A`, in order of starting node.
+    fn ghost_cycles(
+        directions: &[Direction],
+        network: &HashMap<Node, (Node, Node)>,
+    ) -> Vec<(Node, Cycle, Vec<usize>)> {
+        let at_dst = |node: &Node| node.ends_with('Z');
+        network
+            .keys()
+            .filter(|n| n.ends_with('A'))
+            .sorted()
+            .map(|src| {
+                let (cycle, hits) = Self::ghost_cycle(directions, network, src.clone(), &at_dst);
+                (src.clone(), cycle, hits)
+            })
+            .collect()
+    }
+
+    /// Where each ghost starts walking in circles, and the steps on which it is at a destination.
+    pub fn cycle_report(input: &str) -> String {
+        let (directions, network) = Self::parse_input(input);
+        let mut out = String::new();
+        for (src, cycle, hits) in Self::ghost_cycles(&directions, &network) {
+            writeln!(
+                out,
+                "{}: {}, at a destination on steps {:?}",
+                src, cycle, hits
+            )
+            .unwrap();
+        }
+        out
+    }
 }
 
 impl Solution for Day08 {
@@ -81,33 +143,27 @@ impl Solution for Day08 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let (directions, network) = _parsed_input;
-        let at_dst = |node: &Node| node.ends_with('Z');
-        let srcs = network
-            .keys()
-            .filter(|n| n.ends_with('A'))
-            .map(|n| n.to_string());
-        let (dsts, paths): (Vec<Node>, Vec<usize>) = srcs
-            .map(|src| Self::traverse(&directions, &network, 0, src, &at_dst))
-            .unzip();
-        {
-            // assert that your solution works for the given input
-            let cycles: Vec<usize> = dsts
-                .into_iter()
-                .enumerate()
-                .map(|(j, dst)| {
-                    let i = paths[j] % directions.len();
-                    let at_dst = |node: &Node| node == &dst;
-                    Self::traverse(&directions, &network, i, dst.clone(), &at_dst).1
-                })
-                .collect();
-            assert!(
-                paths.iter().zip(cycles.iter()).all(|(p, c)| p == c),
-                "paths {:?} != cycles {:?}",
-                paths,
-                cycles
-            );
-        }
-        paths.into_iter().fold(1, |acc, n| lcm(acc, n)).to_string()
+        Self::ghost_cycles(directions, network)
+            .into_iter()
+            .map(|(src, cycle, hits)| {
+                // the lcm only works if the ghost is at a destination exactly every `first` steps
+                let end = cycle.prefix + cycle.period;
+                let first = *hits.iter().find(|&&i| i > 0).unwrap_or_else(|| {
+                    panic!("Day 8: ghost from {} never reaches a destination", src)
+                });
+                assert!(
+                    cycle.period % first == 0
+                        && hits.iter().copied().eq((0..end).step_by(first).skip(1)),
+                    "Day 8: ghost from {} is at a destination at steps {:?}, not every {} steps ({})",
+                    src,
+                    hits,
+                    first,
+                    cycle
+                );
+                first
+            })
+            .fold(1, lcm)
+            .to_string()
     }
 }
 
@@ -165,4 +221,62 @@ XXX = (XXX, XXX)"
             "6".to_string()
         )
     }
+
+    #[test]
+    fn check_day08_ghost_cycles() {
+        let (directions, network) = Day08::parse_input(
+            "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)",
+        );
+        let at_dst = |node: &Node| node.ends_with('Z');
+        assert_eq!(
+            Day08::ghost_cycle(&directions, &network, "11A".to_string(), &at_dst),
+            (
+                Cycle {
+                    prefix: 1,
+                    period: 2
+                },
+                vec![2]
+            )
+        );
+        assert_eq!(
+            Day08::ghost_cycle(&directions, &network, "22A".to_string(), &at_dst),
+            (
+                Cycle {
+                    prefix: 1,
+                    period: 6
+                },
+                vec![3, 6]
+            )
+        );
+    }
+
+    #[test]
+    fn check_day08_cycle_report() {
+        let report = Day08::cycle_report(
+            "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)",
+        );
+        assert_eq!(
+            report,
+            "11A: repeats every 2 after the first 1, at a destination on steps [2]\n\
+             22A: repeats every 6 after the first 1, at a destination on steps [3, 6]\n"
+        );
+    }
 }
@@ -1,10 +1,10 @@
+use crate::polygon;
 use crate::Solution;
-use itertools::Itertools;
-use std::collections::HashSet;
+use std::fmt;
 
 pub struct Day10;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Direction {
     N,
     E,
@@ -12,185 +12,285 @@ pub enum Direction {
     W,
 }
 
-#[derive(Copy, Clone)]
-pub enum Turn {
-    Left,
-    Right,
+/// What a tile turns out to be once the main loop is known.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tile {
+    /// Part of the main loop, with its pipe (the inferred one under `S`).
+    Loop(char),
+    Inside,
+    Outside,
 }
 
-impl Day10 {
-    fn move_from(row: usize, col: usize, next_from: Direction) -> (usize, usize) {
-        match next_from {
-            Direction::N => (row + 1, col),
-            Direction::E => (row, col.saturating_sub(1)),
-            Direction::S => (row.saturating_sub(1), col),
-            Direction::W => (row, col + 1),
+impl Tile {
+    fn draw(&self) -> char {
+        match self {
+            Tile::Loop('|') => '│',
+            Tile::Loop('-') => '─',
+            Tile::Loop('L') => '└',
+            Tile::Loop('J') => '┘',
+            Tile::Loop('7') => '┐',
+            Tile::Loop('F') => '┌',
+            Tile::Loop(pipe) => *pipe,
+            Tile::Inside => 'I',
+            Tile::Outside => 'O',
         }
     }
+}
 
-    fn get_turn(from: Direction, next_from: Direction) -> Option<Turn> {
-        match (from, next_from) {
-            (Direction::N, Direction::N) => None,
-            (Direction::S, Direction::S) => None,
-            (Direction::E, Direction::E) => None,
-            (Direction::W, Direction::W) => None,
-            (Direction::N, Direction::W) => Some(Turn::Left),
-            (Direction::N, Direction::E) => Some(Turn::Right),
-            (Direction::E, Direction::N) => Some(Turn::Left),
-            (Direction::E, Direction::S) => Some(Turn::Right),
-            (Direction::S, Direction::E) => Some(Turn::Left),
-            (Direction::S, Direction::W) => Some(Turn::Right),
-            (Direction::W, Direction::S) => Some(Turn::Left),
-            (Direction::W, Direction::N) => Some(Turn::Right),
-            _ => panic!("Day 10: Invalid pair of directions"),
+impl Direction {
+    fn opposite(&self) -> Direction {
+        match self {
+            Direction::N => Direction::S,
+            Direction::E => Direction::W,
+            Direction::S => Direction::N,
+            Direction::W => Direction::E,
         }
     }
+}
 
-    fn get_next_from(pipe: char, from: Direction) -> Direction {
-        match (pipe, from) {
-            ('|', Direction::N) => Direction::N,
-            ('|', Direction::S) => Direction::S,
-            ('-', Direction::E) => Direction::E,
-            ('-', Direction::W) => Direction::W,
-            ('L', Direction::N) => Direction::W,
-            ('L', Direction::E) => Direction::S,
-            ('J', Direction::N) => Direction::E,
-            ('J', Direction::W) => Direction::S,
-            ('7', Direction::S) => Direction::E,
-            ('7', Direction::W) => Direction::N,
-            ('F', Direction::S) => Direction::W,
-            ('F', Direction::E) => Direction::N,
-            _ => panic!("Day 10: Invalid direction for pipe {}", pipe),
+/// The loop through `S`, as the positions after each step (ending back at `S`)
+/// with the side each was entered from, plus the pipe inferred to be under `S`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MainLoop {
+    pub start_pipe: char,
+    pub positions: Vec<(usize, usize, Direction)>,
+}
+
+/// Why the pipes don't form a loop through `S`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoopError {
+    NoStart,
+    SeveralStarts(Vec<(usize, usize)>),
+    /// Fewer than two neighbouring pipes connect to `S`; these are the sides that do.
+    StartNotConnected(Vec<Direction>),
+    /// None of the pipes out of `S` lead back to it.
+    Broken(Vec<Break>),
+}
+
+/// Where following the pipes out of one side of `S` broke off.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Break {
+    /// The side of `S` the path left from.
+    pub side: Direction,
+    /// The last tile the path got to.
+    pub at: (usize, usize),
+    /// The pipe the path tried to go into next, or `None` if it ran off the grid.
+    pub next: Option<char>,
+}
+
+impl fmt::Display for LoopError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoopError::NoStart => write!(f, "there is no S"),
+            LoopError::SeveralStarts(starts) => write!(f, "there are several S: {:?}", starts),
+            LoopError::StartNotConnected(sides) => {
+                write!(f, "only {} pipes connect to S ({:?})", sides.len(), sides)
+            }
+            LoopError::Broken(breaks) => {
+                write!(f, "no pipes lead back to S")?;
+                for b in breaks {
+                    let next = match b.next {
+                        Some(pipe) => format!("doesn't connect to {:?}", pipe),
+                        None => "runs off the grid".to_string(),
+                    };
+                    write!(f, "; from its {:?} side, {:?} {}", b.side, b.at, next)?;
+                }
+                Ok(())
+            }
         }
     }
+}
 
-    fn follow_pipe(
-        pipes: &Vec<Vec<char>>,
-        position: (usize, usize, Direction),
-    ) -> (usize, usize, Direction) {
-        let (row, col, from) = position;
-        let pipe = pipes[row][col];
-        let next_from = Self::get_next_from(pipe, from);
-        let (next_row, next_col) = Self::move_from(row, col, next_from);
-        (next_row, next_col, next_from)
+impl Day10 {
+    /// Where a step "from" `next_from` goes, i.e. towards the opposite side,
+    /// or `None` if that is off the grid.
+    fn move_from(
+        pipes: &[Vec<char>],
+        row: usize,
+        col: usize,
+        next_from: Direction,
+    ) -> Option<(usize, usize)> {
+        let (row, col) = match next_from {
+            Direction::N => (row + 1, col),
+            Direction::E => (row, col.checked_sub(1)?),
+            Direction::S => (row.checked_sub(1)?, col),
+            Direction::W => (row, col + 1),
+        };
+        pipes.get(row)?.get(col)?;
+        Some((row, col))
     }
 
-    fn is_valid_pipe(pipe: char, from: Direction) -> bool {
-        match from {
-            Direction::N => "|LJ".contains(pipe),
-            Direction::E => "-LF".contains(pipe),
-            Direction::S => "|7F".contains(pipe),
-            Direction::W => "-J7".contains(pipe),
+    /// Which side a path entering `pipe` from `from` leaves on, if the pipe connects that way.
+    fn get_next_from(pipe: char, from: Direction) -> Option<Direction> {
+        match (pipe, from) {
+            ('|', Direction::N) => Some(Direction::N),
+            ('|', Direction::S) => Some(Direction::S),
+            ('-', Direction::E) => Some(Direction::E),
+            ('-', Direction::W) => Some(Direction::W),
+            ('L', Direction::N) => Some(Direction::W),
+            ('L', Direction::E) => Some(Direction::S),
+            ('J', Direction::N) => Some(Direction::E),
+            ('J', Direction::W) => Some(Direction::S),
+            ('7', Direction::S) => Some(Direction::E),
+            ('7', Direction::W) => Some(Direction::N),
+            ('F', Direction::S) => Some(Direction::W),
+            ('F', Direction::E) => Some(Direction::N),
+            _ => None,
         }
     }
 
-    fn find_start(pipes: &Vec<Vec<char>>) -> (usize, usize) {
+    fn find_starts(pipes: &[Vec<char>]) -> Vec<(usize, usize)> {
         pipes
             .iter()
             .enumerate()
-            .filter_map(|(r, row)| {
+            .flat_map(|(r, row)| {
                 row.iter()
                     .enumerate()
-                    .filter_map(|(c, &pipe)| if pipe == 'S' { Some((r, c)) } else { None })
-                    .next()
+                    .filter(|&(_, &pipe)| pipe == 'S')
+                    .map(move |(c, _)| (r, c))
             })
-            .next()
-            .unwrap()
+            .collect()
     }
 
-    fn from_start(
-        pipes: &Vec<Vec<char>>,
+    /// Follow the pipes out of the `side` side of `start` until they come back to it.
+    fn trace(
+        pipes: &[Vec<char>],
         start: (usize, usize),
-    ) -> ((usize, usize, Direction), (usize, usize, Direction)) {
-        let (row, col) = start;
-        [Direction::N, Direction::E, Direction::S, Direction::W]
+        side: Direction,
+    ) -> Result<Vec<(usize, usize, Direction)>, Break> {
+        let (mut row, mut col) = start;
+        let mut from = side.opposite();
+        let mut positions = Vec::new();
+        loop {
+            let Some((next_row, next_col)) = Self::move_from(pipes, row, col, from) else {
+                return Err(Break {
+                    side,
+                    at: (row, col),
+                    next: None,
+                });
+            };
+            if (next_row, next_col) == start {
+                positions.push((next_row, next_col, from));
+                return Ok(positions);
+            }
+            let pipe = pipes[next_row][next_col];
+            let Some(next_from) = Self::get_next_from(pipe, from) else {
+                return Err(Break {
+                    side,
+                    at: (row, col),
+                    next: Some(pipe),
+                });
+            };
+            positions.push((next_row, next_col, from));
+            (row, col, from) = (next_row, next_col, next_from);
+        }
+    }
+
+    /// Find the loop through `S`. When more than two pipes connect to `S`, every way out
+    /// is followed and the longest one that closes back on `S` is taken as the main loop.
+    pub fn validate(pipes: &[Vec<char>]) -> Result<MainLoop, LoopError> {
+        let start = match Self::find_starts(pipes)[..] {
+            [] => return Err(LoopError::NoStart),
+            [start] => start,
+            ref starts => return Err(LoopError::SeveralStarts(starts.to_vec())),
+        };
+        let sides = [Direction::N, Direction::E, Direction::S, Direction::W]
             .into_iter()
-            .filter_map(|next_from| {
-                let (next_row, next_col) = Self::move_from(row, col, next_from);
-                if let Some(row) = pipes.get(next_row) {
-                    if let Some(pipe) = row.get(next_col) {
-                        if Self::is_valid_pipe(*pipe, next_from) {
-                            return Some((next_row, next_col, next_from));
-                        }
+            .filter(|side| {
+                let from = side.opposite();
+                Self::move_from(pipes, start.0, start.1, from)
+                    .is_some_and(|(r, c)| Self::get_next_from(pipes[r][c], from).is_some())
+            })
+            .collect::<Vec<_>>();
+        if sides.len() < 2 {
+            return Err(LoopError::StartNotConnected(sides));
+        }
+        let mut breaks = Vec::new();
+        let mut longest: Option<Vec<_>> = None;
+        for &side in sides.iter() {
+            match Self::trace(pipes, start, side) {
+                Ok(positions) => {
+                    if longest.as_ref().is_none_or(|l| positions.len() > l.len()) {
+                        longest = Some(positions);
                     }
                 }
-                return None;
-            })
-            .collect_tuple()
-            .unwrap()
-    }
-
-    fn find_loop(pipes: &Vec<Vec<char>>) -> Vec<(usize, usize, Direction)> {
-        let start = Self::find_start(&pipes);
-        let mut a = Self::from_start(&pipes, start).0;
-        let mut positions = Vec::from([a]);
-        while (a.0, a.1) != (start.0, start.1) {
-            a = Self::follow_pipe(&pipes, a);
-            positions.push(a);
+                Err(b) => breaks.push(b),
+            }
         }
-        positions
+        let positions = longest.ok_or(LoopError::Broken(breaks))?;
+        Ok(MainLoop {
+            start_pipe: Self::start_pipe(&positions),
+            positions,
+        })
     }
 
-    fn is_counterclockwise(main_loop: &Vec<(usize, usize, Direction)>) -> bool {
-        let last_from = main_loop[main_loop.len() - 1].2;
-        let ((lefts, rights), _) = main_loop.iter().map(|&(_, _, from)| from).fold(
-            ((0, 0), last_from),
-            |((lefts, rights), prev_from), from| match Self::get_turn(prev_from, from) {
-                Some(Turn::Left) => ((lefts + 1, rights), from),
-                Some(Turn::Right) => ((lefts, rights + 1), from),
-                None => ((lefts, rights), from),
-            },
-        );
-        lefts > rights
+    fn find_loop(pipes: &[Vec<char>]) -> Vec<(usize, usize, Direction)> {
+        Self::validate(pipes)
+            .unwrap_or_else(|e| panic!("Day 10: {}", e))
+            .positions
     }
 
-    fn step_inward(r: usize, c: usize, direction: Direction, ccw: bool) -> (usize, usize) {
-        match (direction, ccw) {
-            (Direction::N, true) => Self::move_from(r, c, Direction::W),
-            (Direction::E, true) => Self::move_from(r, c, Direction::N),
-            (Direction::S, true) => Self::move_from(r, c, Direction::E),
-            (Direction::W, true) => Self::move_from(r, c, Direction::S),
-            (Direction::N, false) => Self::move_from(r, c, Direction::E),
-            (Direction::E, false) => Self::move_from(r, c, Direction::S),
-            (Direction::S, false) => Self::move_from(r, c, Direction::W),
-            (Direction::W, false) => Self::move_from(r, c, Direction::N),
+    /// The pipe under `S`, from the way the loop leaves it and the way it comes back.
+    fn start_pipe(main_loop: &[(usize, usize, Direction)]) -> char {
+        // a step "from" a side moves away from it, so the loop leaves `S` on the opposite side
+        let leaves = main_loop[0].2.opposite();
+        let returns = main_loop[main_loop.len() - 1].2;
+        match (leaves, returns) {
+            (Direction::N, Direction::S) | (Direction::S, Direction::N) => '|',
+            (Direction::E, Direction::W) | (Direction::W, Direction::E) => '-',
+            (Direction::N, Direction::E) | (Direction::E, Direction::N) => 'L',
+            (Direction::N, Direction::W) | (Direction::W, Direction::N) => 'J',
+            (Direction::S, Direction::W) | (Direction::W, Direction::S) => '7',
+            (Direction::S, Direction::E) | (Direction::E, Direction::S) => 'F',
+            _ => panic!("Day 10: the loop leaves and returns to S on the same side"),
         }
     }
 
-    fn count_enclosed_tiles(
-        pipes: &Vec<Vec<char>>,
-        main_loop: Vec<(usize, usize, Direction)>,
-    ) -> usize {
-        let ccw = Self::is_counterclockwise(&main_loop);
-        let loop_locations = main_loop
+    /// Every tile classified against the main loop, with junk pipes off the loop
+    /// counted as inside or outside like any other tile.
+    pub fn classify(pipes: &[Vec<char>]) -> Vec<Vec<Tile>> {
+        let MainLoop {
+            start_pipe,
+            positions: main_loop,
+        } = Self::validate(pipes).unwrap_or_else(|e| panic!("Day 10: {}", e));
+        let mut tiles = pipes
             .iter()
-            .map(|&(r, c, _)| (r, c))
-            .collect::<HashSet<(usize, usize)>>();
-        // find initial positions
-        let mut candidates = Vec::new();
-        for (i, &(r, c, from)) in main_loop.iter().enumerate() {
-            let next_from = main_loop[(i + 1) % main_loop.len()].2;
-            candidates.push(Self::step_inward(r, c, from, ccw));
-            candidates.push(Self::step_inward(r, c, next_from, ccw));
+            .map(|row| vec![Tile::Outside; row.len()])
+            .collect::<Vec<_>>();
+        for &(r, c, _) in main_loop.iter() {
+            let pipe = pipes[r][c];
+            tiles[r][c] = Tile::Loop(if pipe == 'S' { start_pipe } else { pipe });
         }
-        candidates.dedup();
-        candidates.retain(|&(r, c)| c < pipes[r].len() && !loop_locations.contains(&(r, c)));
-        // fill from initial positions
-        let mut enclosed_tiles = HashSet::new();
-        while let Some((r, c)) = candidates.pop() {
-            if loop_locations.contains(&(r, c)) || enclosed_tiles.contains(&(r, c)) {
-                continue;
+        // scanning along a row, crossing a loop pipe that reaches north flips inside and out
+        for row in tiles.iter_mut() {
+            let mut inside = false;
+            for tile in row.iter_mut() {
+                match *tile {
+                    Tile::Loop(pipe) => inside ^= "|LJ".contains(pipe),
+                    _ if inside => *tile = Tile::Inside,
+                    _ => {}
+                }
             }
-            enclosed_tiles.insert((r, c));
-            candidates.append(
-                &mut [Direction::N, Direction::E, Direction::S, Direction::W]
-                    .into_iter()
-                    .map(|direction| Self::move_from(r, c, direction))
-                    .filter(|&(r, c)| c < pipes[r].len())
-                    .collect::<Vec<(usize, usize)>>(),
-            );
         }
-        enclosed_tiles.len()
+        tiles
+    }
+
+    /// The pipes redrawn with box-drawing characters, showing only the main loop and
+    /// marking every other tile `I` or `O` for inside or outside it.
+    pub fn render(pipes: &[Vec<char>]) -> String {
+        Self::classify(pipes)
+            .iter()
+            .map(|row| row.iter().map(Tile::draw).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn count_enclosed_tiles(main_loop: &[(usize, usize, Direction)]) -> usize {
+        let vertices = main_loop
+            .iter()
+            .map(|&(r, c, _)| (r as isize, c as isize))
+            .collect::<Vec<_>>();
+        polygon::interior_points(&vertices) as usize
     }
 }
 
@@ -212,7 +312,7 @@ impl Solution for Day10 {
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let pipes = _parsed_input;
         let main_loop = Self::find_loop(&pipes);
-        Self::count_enclosed_tiles(&pipes, main_loop).to_string()
+        Self::count_enclosed_tiles(&main_loop).to_string()
     }
 }
 
@@ -321,4 +421,142 @@ L7JLJL-JLJLJL--JLJ.L"
             "10".to_string()
         )
     }
+
+    #[test]
+    fn check_day10_render() {
+        let pipes = Day10::parse_input(
+            "..........
+.S------7.
+.|F----7|.
+.||OOOO||.
+.||OOOO||.
+.|L-7F-J|.
+.|II||II|.
+.L--JL--J.
+..........",
+        );
+        let expected = "OOOOOOOOOO
+O┌──────┐O
+O│┌────┐│O
+O││OOOO││O
+O││OOOO││O
+O│└─┐┌─┘│O
+O│II││II│O
+O└──┘└──┘O
+OOOOOOOOOO";
+        assert_eq!(Day10::render(&pipes), expected);
+
+        // junk pipes are hidden, and the counts agree with part two
+        let pipes = Day10::parse_input(
+            "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L",
+        );
+        let rendered = Day10::render(&pipes);
+        assert!(rendered.starts_with("O┌┐┌┐┌┐┌┐┌┐┌┐┌┐┌───┐\nO│└┘││││"));
+        let main_loop = Day10::find_loop(&pipes);
+        assert_eq!(
+            rendered.matches('I').count(),
+            Day10::count_enclosed_tiles(&main_loop)
+        );
+        assert!(!rendered.contains(|c: char| "S|-LJ7F.".contains(c)));
+    }
+
+    #[test]
+    fn check_day10_ambiguous_start() {
+        // three pipes connect to S, but the one on the left leads off the grid
+        let pipes = Day10::parse_input(
+            ".F-7.
+-S.|.
+.L-J.",
+        );
+        let main_loop = Day10::validate(&pipes).unwrap();
+        assert_eq!(main_loop.start_pipe, '|');
+        assert_eq!(main_loop.positions.len(), 8);
+        assert_eq!(Day10::count_enclosed_tiles(&main_loop.positions), 1);
+
+        // S in the corner, with a loop either way round
+        let pipes = Day10::parse_input("S-7\n|.|\nL-J");
+        let main_loop = Day10::validate(&pipes).unwrap();
+        assert_eq!(main_loop.start_pipe, 'F');
+        assert_eq!(Day10::render(&pipes), "┌─┐\n│I│\n└─┘");
+
+        // two loops meeting at S, where the bigger one wins
+        let pipes = Day10::parse_input(
+            "F7...
+LS--7
+.|..|
+.L--J",
+        );
+        let main_loop = Day10::validate(&pipes).unwrap();
+        assert_eq!(main_loop.start_pipe, 'F');
+        assert_eq!(main_loop.positions.len(), 10);
+    }
+
+    #[test]
+    fn check_day10_broken_loops() {
+        let pipes = Day10::parse_input(
+            ".F-7.
+.S.|.
+.L-..",
+        );
+        let Err(LoopError::Broken(breaks)) = Day10::validate(&pipes) else {
+            panic!("expected a broken loop");
+        };
+        assert_eq!(breaks.len(), 2);
+        assert!(breaks.iter().all(|b| b.next == Some('.')));
+        assert_eq!(breaks[0].side, Direction::N);
+        assert_eq!(breaks[0].at, (1, 3));
+        assert_eq!(breaks[1].at, (2, 2));
+
+        let pipes = Day10::parse_input("S-\n..");
+        assert_eq!(
+            Day10::validate(&pipes),
+            Err(LoopError::StartNotConnected(vec![Direction::E]))
+        );
+        let pipes = Day10::parse_input("S-7\n|.|\nL-S");
+        assert_eq!(
+            Day10::validate(&pipes),
+            Err(LoopError::SeveralStarts(vec![(0, 0), (2, 2)]))
+        );
+        assert_eq!(
+            Day10::validate(&Day10::parse_input("F7\nLJ")),
+            Err(LoopError::NoStart)
+        );
+        let error = Day10::validate(&Day10::parse_input("S-\n|."))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "no pipes lead back to S; from its E side, (0, 1) runs off the grid; \
+             from its S side, (1, 0) runs off the grid"
+        );
+    }
+
+    #[test]
+    fn check_day10_either_direction() {
+        let pipes = Day10::parse_input(
+            ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...",
+        );
+        let mut main_loop = Day10::find_loop(&pipes);
+        assert_eq!(Day10::count_enclosed_tiles(&main_loop), 8);
+        main_loop.reverse();
+        assert_eq!(Day10::count_enclosed_tiles(&main_loop), 8);
+    }
 }
@@ -1,6 +1,7 @@
+use crate::cycles::{self, Cycle, Method};
 use crate::Solution;
 use itertools::Either;
-use std::collections::HashMap;
+use std::fmt::Write;
 
 pub struct Day14;
 
@@ -53,24 +54,30 @@ impl Day14 {
         Self::tilt(platform, 0, 1); // E
     }
 
-    fn spin_cycles(platform: &mut Vec<Vec<char>>, cycles: usize) -> () {
-        let mut map = HashMap::<Vec<Vec<char>>, usize>::new();
-        let mut cycle = 0;
-        while cycle < cycles {
-            if let Some(last_cycle) = map.get(platform) {
-                let remaining = (cycles - cycle) % (cycle - last_cycle);
-                cycle = cycles - remaining; // skip ahead
-                break;
-            } else {
-                map.insert(platform.clone(), cycle);
-                Self::spin_cycle(platform);
-                cycle += 1;
-            }
-        }
-        while cycle < cycles {
-            Self::spin_cycle(platform);
-            cycle += 1;
-        }
+    pub fn spin_cycles(platform: &mut Vec<Vec<char>>, cycles: usize) -> Cycle {
+        let spin = |platform: &Vec<Vec<char>>| {
+            let mut next = platform.clone();
+            Self::spin_cycle(&mut next);
+            next
+        };
+        let (spun, cycle) = cycles::state_after(platform, spin, cycles, Method::Hashed);
+        *platform = spun;
+        cycle
+    }
+
+    /// The period of the platform under spin cycles, and the load it settles into.
+    pub fn cycle_report(input: &str) -> String {
+        let platform = &mut Self::parse_input(input);
+        let cycle = Self::spin_cycles(platform, 1000000000);
+        let mut out = String::new();
+        writeln!(out, "spin cycles: {}", cycle).unwrap();
+        writeln!(
+            out,
+            "load after 1000000000 spin cycles: {}",
+            Self::calculate_load(platform)
+        )
+        .unwrap();
+        out
     }
 
     fn calculate_load(platform: &Vec<Vec<char>>) -> usize {
@@ -131,4 +138,21 @@ O.#..O.#.#
     fn check_day14_part2_case1() {
         assert_eq!(Day14::solve_part_two(TEST_INPUT), "64".to_string())
     }
+
+    #[test]
+    fn check_day14_spin_cycle_period() {
+        let platform = &mut Day14::parse_input(TEST_INPUT);
+        assert_eq!(
+            Day14::spin_cycles(platform, 1000000000),
+            Cycle {
+                prefix: 3,
+                period: 7
+            }
+        );
+        assert_eq!(
+            Day14::cycle_report(TEST_INPUT),
+            "spin cycles: repeats every 7 after the first 3\n\
+             load after 1000000000 spin cycles: 64\n"
+        );
+    }
 }
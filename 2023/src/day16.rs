@@ -1,9 +1,14 @@
+use crate::bitset::BitSet;
 use crate::Solution;
-use std::collections::HashSet;
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+use std::rc::Rc;
 
 pub struct Day16;
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Beam {
     r: usize,
     c: usize,
@@ -11,6 +16,113 @@ pub struct Beam {
     dc: isize,
 }
 
+impl Beam {
+    /// A beam at `(r, c)` heading `(dr, dc)`, e.g. `(0, 1)` to the right.
+    pub fn new(r: usize, c: usize, dr: isize, dc: isize) -> Self {
+        Beam { r, c, dr, dc }
+    }
+}
+
+/// Every straight run of beam reachable from a set of entry beams, each running from
+/// where it enters a tile up to the next mirror or splitter that changes its course.
+/// Runs that feed into each other in a loop are collapsed into one component, and each
+/// component's energized tiles are worked out once and shared with everything upstream.
+pub struct Segments {
+    cols: usize,
+    index: HashMap<Beam, usize>,
+    component: Vec<usize>,
+    energized: Vec<Rc<BitSet>>,
+}
+
+impl Segments {
+    pub fn new(grid: &[Vec<char>], entries: &[Beam]) -> Self {
+        let (rows, cols) = (grid.len(), grid[0].len());
+        let mut index = HashMap::new();
+        let mut tiles = Vec::new();
+        let mut exits = Vec::new();
+        let mut queue = entries.to_vec();
+        while let Some(beam) = queue.pop() {
+            if index.contains_key(&beam) {
+                continue;
+            }
+            index.insert(beam, tiles.len());
+            let (covered, next) = Day16::segment(grid, beam);
+            queue.extend(next.iter().copied());
+            tiles.push(covered);
+            exits.push(next);
+        }
+
+        let mut graph = DiGraph::<(), ()>::with_capacity(tiles.len(), tiles.len());
+        for _ in 0..tiles.len() {
+            graph.add_node(());
+        }
+        for (i, next) in exits.iter().enumerate() {
+            for beam in next {
+                graph.add_edge(NodeIndex::new(i), NodeIndex::new(index[beam]), ());
+            }
+        }
+        // components come out downstream first, so their successors are always done
+        let components = tarjan_scc(&graph);
+        let mut component = vec![0; tiles.len()];
+        for (k, members) in components.iter().enumerate() {
+            for member in members {
+                component[member.index()] = k;
+            }
+        }
+
+        let mut energized: Vec<Rc<BitSet>> = Vec::with_capacity(components.len());
+        for (k, members) in components.iter().enumerate() {
+            let mut set: Option<Rc<BitSet>> = None;
+            for member in members {
+                for beam in exits[member.index()].iter() {
+                    let j = component[index[beam]];
+                    if j == k {
+                        continue;
+                    }
+                    set = Some(match set.take() {
+                        None => energized[j].clone(),
+                        Some(mut set) => {
+                            if !energized[j].is_subset(&set) {
+                                Rc::make_mut(&mut set).union_with(&energized[j]);
+                            }
+                            set
+                        }
+                    });
+                }
+            }
+            let mut set = set.unwrap_or_else(|| Rc::new(BitSet::new(rows * cols)));
+            for member in members {
+                for &tile in tiles[member.index()].iter() {
+                    if !set.contains(tile) {
+                        Rc::make_mut(&mut set).insert(tile);
+                    }
+                }
+            }
+            energized.push(set);
+        }
+
+        Segments {
+            cols,
+            index,
+            component,
+            energized,
+        }
+    }
+
+    /// The tiles energized by `entry`, which must be one of the entries the segments
+    /// were built from, as `r * cols + c`.
+    pub fn energized(&self, entry: &Beam) -> &BitSet {
+        &self.energized[self.component[self.index[entry]]]
+    }
+
+    pub fn energized_tiles(&self, entry: &Beam) -> HashSet<(usize, usize)> {
+        self.energized(entry)
+            .iter()
+            .map(|i| (i / self.cols, i % self.cols))
+            .collect()
+    }
+}
+
 impl Day16 {
     fn move_beam(beam: &Beam, bounds: (usize, usize)) -> Option<Beam> {
         if (beam.r == 0 && beam.dr < 0)
@@ -30,92 +142,157 @@ impl Day16 {
         })
     }
 
-    fn energized_tiles(grid: &Vec<Vec<char>>, initial_beam: Beam) -> HashSet<(usize, usize)> {
-        // apologies for the spaghetti
-        let mut energized = HashSet::new();
+    /// The directions a beam heading `(dr, dc)` leaves `tile` in.
+    fn deflect(tile: char, dr: isize, dc: isize) -> Vec<(isize, isize)> {
+        match tile {
+            '/' => vec![(-dc, -dr)],
+            '\\' => vec![(dc, dr)],
+            // a splitter hit side-on splits the beam, otherwise it passes straight through
+            '|' if dc != 0 => vec![(-1, 0), (1, 0)],
+            '-' if dr != 0 => vec![(0, 1), (0, -1)],
+            _ => vec![(dr, dc)],
+        }
+    }
+
+    /// Every (tile, direction) a beam starting as `initial_beam` passes through.
+    fn beams(grid: &[Vec<char>], initial_beam: Beam) -> HashSet<Beam> {
         let mut cache = HashSet::new();
         let mut beams = Vec::from([initial_beam]);
         let bounds = (grid.len(), grid[0].len());
-        while beams.len() > 0 {
-            let mut beam = beams.pop().unwrap();
-            if cache.contains(&beam) {
+        while let Some(beam) = beams.pop() {
+            if !cache.insert(beam) {
                 continue;
             }
-            cache.insert(beam);
-            energized.insert((beam.r, beam.c));
-            let tile = grid[beam.r][beam.c];
-            if tile == '/' {
-                (beam.dr, beam.dc) = (beam.dc * -1, beam.dr * -1);
-                if let Some(new_beam) = Self::move_beam(&beam, bounds) {
-                    beams.push(new_beam);
-                }
-            } else if tile == '\\' {
-                (beam.dr, beam.dc) = (beam.dc, beam.dr);
-                if let Some(new_beam) = Self::move_beam(&beam, bounds) {
-                    beams.push(new_beam);
-                }
-            } else if tile == '|' {
-                if beam.dc == 0 {
-                    // up or down, keep going
-                    if let Some(new_beam) = Self::move_beam(&beam, bounds) {
-                        beams.push(new_beam);
-                    }
-                } else {
-                    // left or right, split
-                    let a = Beam {
-                        r: beam.r,
-                        c: beam.c,
-                        dr: -1,
-                        dc: 0,
-                    };
-                    let b = Beam {
-                        r: beam.r,
-                        c: beam.c,
-                        dr: 1,
-                        dc: 0,
-                    };
-                    if let Some(new_beam) = Self::move_beam(&a, bounds) {
-                        beams.push(new_beam);
-                    }
-                    if let Some(new_beam) = Self::move_beam(&b, bounds) {
-                        beams.push(new_beam);
-                    }
-                }
-            } else if tile == '-' {
-                if beam.dr == 0 {
-                    // left or right, keep going
-                    if let Some(new_beam) = Self::move_beam(&beam, bounds) {
-                        beams.push(new_beam);
-                    }
-                } else {
-                    // up or down, split
-                    let a = Beam {
-                        r: beam.r,
-                        c: beam.c,
-                        dr: 0,
-                        dc: 1,
-                    };
-                    let b = Beam {
-                        r: beam.r,
-                        c: beam.c,
-                        dr: 0,
-                        dc: -1,
-                    };
-                    if let Some(new_beam) = Self::move_beam(&a, bounds) {
-                        beams.push(new_beam);
-                    }
-                    if let Some(new_beam) = Self::move_beam(&b, bounds) {
-                        beams.push(new_beam);
-                    }
-                }
+            for (dr, dc) in Self::deflect(grid[beam.r][beam.c], beam.dr, beam.dc) {
+                beams.extend(Self::move_beam(&Beam { dr, dc, ..beam }, bounds));
+            }
+        }
+        cache
+    }
+
+    pub fn energized_tiles(grid: &[Vec<char>], initial_beam: Beam) -> HashSet<(usize, usize)> {
+        Self::beams(grid, initial_beam)
+            .iter()
+            .map(|beam| (beam.r, beam.c))
+            .collect()
+    }
+
+    /// Follow `beam` in a straight line until something changes its course or it leaves
+    /// the grid, returning the tiles passed (as `r * cols + c`) and the beams it turns into.
+    fn segment(grid: &[Vec<char>], mut beam: Beam) -> (Vec<usize>, Vec<Beam>) {
+        let bounds = (grid.len(), grid[0].len());
+        let mut tiles = Vec::new();
+        loop {
+            tiles.push(beam.r * bounds.1 + beam.c);
+            let out = Self::deflect(grid[beam.r][beam.c], beam.dr, beam.dc);
+            if out != [(beam.dr, beam.dc)] {
+                let exits = out
+                    .into_iter()
+                    .filter_map(|(dr, dc)| Self::move_beam(&Beam { dr, dc, ..beam }, bounds))
+                    .collect();
+                return (tiles, exits);
+            }
+            match Self::move_beam(&beam, bounds) {
+                Some(next) => beam = next,
+                None => return (tiles, Vec::new()),
+            }
+        }
+    }
+
+    /// The grid with the path of the beam drawn over it as in the puzzle: empty tiles
+    /// show the beam's direction, or how many beams cross them if there are several.
+    pub fn render_beam(grid: &[Vec<char>], initial_beam: Beam) -> String {
+        let mut directions = HashMap::<(usize, usize), Vec<char>>::new();
+        for beam in Self::beams(grid, initial_beam) {
+            let arrow = match (beam.dr, beam.dc) {
+                (-1, 0) => '^',
+                (1, 0) => 'v',
+                (0, -1) => '<',
+                _ => '>',
+            };
+            directions.entry((beam.r, beam.c)).or_default().push(arrow);
+        }
+        Self::render_with(grid, |r, c, tile| match directions.get(&(r, c)) {
+            Some(arrows) if tile == '.' && arrows.len() == 1 => arrows[0],
+            Some(arrows) if tile == '.' => char::from_digit(arrows.len() as u32, 10).unwrap(),
+            _ => tile,
+        })
+    }
+
+    /// The grid with energized tiles as `#` and everything else as `.`.
+    pub fn render_energized(grid: &[Vec<char>], initial_beam: Beam) -> String {
+        let energized = Self::energized_tiles(grid, initial_beam);
+        Self::render_with(grid, |r, c, _| {
+            if energized.contains(&(r, c)) {
+                '#'
             } else {
-                // empty space
-                if let Some(new_beam) = Self::move_beam(&beam, bounds) {
-                    beams.push(new_beam);
-                }
+                '.'
+            }
+        })
+    }
+
+    fn render_with(grid: &[Vec<char>], f: impl Fn(usize, usize, char) -> char) -> String {
+        grid.iter()
+            .enumerate()
+            .map(|(r, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(c, &tile)| f(r, c, tile))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// How many of the entry beams from `initial_beams` energize each tile.
+    pub fn heatmap(grid: &[Vec<char>]) -> Vec<Vec<usize>> {
+        if grid.is_empty() {
+            return Vec::new();
+        }
+        let (rows, cols) = (grid.len(), grid[0].len());
+        let entries = Self::initial_beams(rows, cols);
+        let segments = Segments::new(grid, &entries);
+        let mut counts = vec![vec![0; cols]; rows];
+        for entry in entries.iter() {
+            for (r, c) in segments.energized_tiles(entry) {
+                counts[r][c] += 1;
             }
         }
-        energized
+        counts
+    }
+
+    /// The heatmap as text, from ` ` for tiles that are never energized up to `@`
+    /// for the most often energized ones.
+    pub fn heatmap_text(counts: &[Vec<usize>]) -> String {
+        const SHADES: &[u8] = b" .:-=+*#%@";
+        let max = counts.iter().flatten().copied().max().unwrap_or(0);
+        counts
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&n| {
+                        let shade = (n * (SHADES.len() - 1)).div_ceil(max.max(1));
+                        SHADES[shade] as char
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Write the heatmap as a binary PPM image, one pixel per tile, going from black
+    /// through red and yellow to white as tiles are energized more often.
+    pub fn write_heatmap_ppm(out: &mut impl Write, counts: &[Vec<usize>]) -> io::Result<()> {
+        let (rows, cols) = (counts.len(), counts.first().map_or(0, |row| row.len()));
+        let max = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+        writeln!(out, "P6\n{} {}\n255", cols, rows)?;
+        for &n in counts.iter().flatten() {
+            // spread the heat over the three channels in turn
+            let heat = n * 255 * 3 / max;
+            let channel = |k: usize| heat.saturating_sub(k * 255).min(255) as u8;
+            out.write_all(&[channel(0), channel(1), channel(2)])?;
+        }
+        Ok(())
     }
 
     fn initial_beams(rows: usize, cols: usize) -> Vec<Beam> {
@@ -172,22 +349,17 @@ impl Solution for Day16 {
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
-        let initial_beam = Beam {
-            r: 0,
-            c: 0,
-            dr: 0,
-            dc: 1,
-        };
+        let initial_beam = Beam::new(0, 0, 0, 1);
         Self::energized_tiles(grid, initial_beam).len().to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
         let initial_beams = Self::initial_beams(grid.len(), grid[0].len());
-        // TODO do this more efficiently?
+        let segments = Segments::new(grid, &initial_beams);
         initial_beams
             .iter()
-            .map(|&initial_beam| Self::energized_tiles(grid, initial_beam).len())
+            .map(|initial_beam| segments.energized(initial_beam).len())
             .max()
             .unwrap()
             .to_string()
@@ -218,4 +390,93 @@ mod tests {
     fn check_day16_part2_case1() {
         assert_eq!(Day16::solve_part_two(TEST_INPUT), "51".to_string())
     }
+
+    #[test]
+    fn check_day16_segments() {
+        let mut grids = vec![Day16::parse_input(TEST_INPUT)];
+        // a loop the beam can enter from several sides
+        grids.push(Day16::parse_input(
+            r"..|.\
+.....
+\.-./
+..|..
+\.|.-",
+        ));
+        for grid in grids {
+            let entries = Day16::initial_beams(grid.len(), grid[0].len());
+            let segments = Segments::new(&grid, &entries);
+            assert!(segments.energized.len() < segments.index.len());
+            for entry in entries {
+                assert_eq!(
+                    segments.energized_tiles(&entry),
+                    Day16::energized_tiles(&grid, entry),
+                    "{:?}",
+                    entry
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn check_day16_render() {
+        let grid = Day16::parse_input(TEST_INPUT);
+        let beam = Beam::new(0, 0, 0, 1);
+        let expected = r">|<<<\....
+|v-.\^....
+.v...|->>>
+.v...v^.|.
+.v...v^...
+.v...v^..\
+.v../2\\..
+<->-/vv|..
+.|<<<2-|.\
+.v//.|.v..";
+        assert_eq!(Day16::render_beam(&grid, beam), expected);
+        let expected = "######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#..";
+        assert_eq!(Day16::render_energized(&grid, beam), expected);
+    }
+
+    #[test]
+    fn check_day16_heatmap() {
+        let grid = Day16::parse_input(TEST_INPUT);
+        let counts = Day16::heatmap(&grid);
+        let entries = Day16::initial_beams(grid.len(), grid[0].len());
+        let expected = entries
+            .iter()
+            .filter(|&&entry| Day16::energized_tiles(&grid, entry).contains(&(7, 4)))
+            .count();
+        assert_eq!(counts[7][4], expected);
+        assert_eq!(
+            counts.iter().flatten().sum::<usize>(),
+            entries
+                .iter()
+                .map(|&entry| Day16::energized_tiles(&grid, entry).len())
+                .sum::<usize>()
+        );
+
+        let text = Day16::heatmap_text(&counts);
+        assert_eq!(text.lines().count(), 10);
+        assert!(text.contains('@'));
+        let mut ppm = Vec::new();
+        Day16::write_heatmap_ppm(&mut ppm, &counts).unwrap();
+        let header = b"P6\n10 10\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 3 * 100);
+
+        let counts = Day16::heatmap(&Day16::parse_input(""));
+        assert!(counts.is_empty());
+        assert_eq!(Day16::heatmap_text(&counts), "");
+        let mut ppm = Vec::new();
+        Day16::write_heatmap_ppm(&mut ppm, &counts).unwrap();
+        assert_eq!(ppm, b"P6\n0 0\n255\n");
+    }
 }
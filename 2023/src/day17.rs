@@ -1,7 +1,5 @@
+use crate::search;
 use crate::Solution;
-use core::cmp::Ordering;
-use std::collections::BinaryHeap;
-use std::collections::HashMap;
 
 pub struct Day17;
 
@@ -42,24 +40,77 @@ impl Direction {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-pub struct State {
-    heat_loss: u32,
-    posdir: (usize, usize, Direction),
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Turn {
+    Left,
+    Right,
+    Reverse,
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other
-            .heat_loss
-            .cmp(&self.heat_loss)
-            .then_with(|| self.posdir.cmp(&other.posdir))
+/// How a crucible may move: after each turn it goes between `min_run` and `max_run`
+/// blocks in a straight line, and it can only stop once it has gone at least `min_run`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Movement {
+    pub min_run: usize,
+    pub max_run: usize,
+    /// The turns allowed at the end of a run. The first run can go in any direction.
+    pub turns: Vec<Turn>,
+    pub start: (usize, usize),
+    /// Where the crucible has to get to, or the bottom-right block if `None`.
+    pub goal: Option<(usize, usize)>,
+}
+
+impl Movement {
+    pub fn crucible() -> Self {
+        Movement {
+            min_run: 1,
+            max_run: 3,
+            turns: vec![Turn::Left, Turn::Right],
+            start: (0, 0),
+            goal: None,
+        }
     }
+
+    pub fn ultra_crucible() -> Self {
+        Movement {
+            min_run: 4,
+            max_run: 10,
+            ..Self::crucible()
+        }
+    }
+}
+
+/// One block entered along a route, the direction it was entered in and the heat lost there.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub position: (usize, usize),
+    pub direction: Direction,
+    pub heat_loss: u32,
 }
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub heat_loss: u32,
+    pub steps: Vec<Step>,
+}
+
+impl Direction {
+    fn turn(&self, turn: Turn) -> Direction {
+        match turn {
+            Turn::Left => self.left(),
+            Turn::Right => self.right(),
+            Turn::Reverse => self.left().left(),
+        }
+    }
+
+    /// The arrow the puzzle uses to draw a route heading this way.
+    pub fn arrow(&self) -> char {
+        match self {
+            Direction::N => '^',
+            Direction::E => '>',
+            Direction::S => 'v',
+            Direction::W => '<',
+        }
     }
 }
 
@@ -82,34 +133,23 @@ impl Day17 {
         Some((nr as usize, nc as usize))
     }
 
-    fn least_heat_loss(grid: &Vec<Vec<u32>>, min_steps: usize, max_steps: usize) -> u32 {
-        // dijkstra's algorithm?
+    /// The route with the least heat loss from the start to the goal, or `None` if
+    /// the crucible can't get there.
+    pub fn least_heat_loss(grid: &[Vec<u32>], movement: &Movement) -> Option<Route> {
         let (rows, cols) = (grid.len(), grid[0].len());
         let bounds = (rows, cols);
-        let mut heap = BinaryHeap::new();
-        let mut dist = HashMap::<(usize, usize, Direction), u32>::new();
-        heap.push(State {
-            heat_loss: 0,
-            posdir: (0, 0, Direction::N),
-        });
-        dist.insert((0, 0, Direction::N), 0);
-        heap.push(State {
-            heat_loss: 0,
-            posdir: (0, 0, Direction::W),
-        });
-        dist.insert((0, 0, Direction::W), 0);
-        while let Some(State { heat_loss, posdir }) = heap.pop() {
-            let (r, c, d) = posdir;
-            if (r, c) == (rows - 1, cols - 1) {
-                continue;
+        let goal = movement.goal.unwrap_or((rows - 1, cols - 1));
+        let (sr, sc) = movement.start;
+        // no direction yet at the start, where any way out is allowed
+        let starts = [(sr, sc, None)];
+        let neighbours = |&(r, c, d): &(usize, usize, Option<Direction>)| {
+            let directions = match d {
+                None => vec![Direction::N, Direction::E, Direction::S, Direction::W],
+                Some(d) => movement.turns.iter().map(|&t| d.turn(t)).collect(),
             };
-            if let Some(&min_heat_loss) = dist.get(&(r, c, d)) {
-                if heat_loss > min_heat_loss {
-                    continue;
-                }
-            }
-            for nd in [d.left(), d.right()] {
-                for i in min_steps..=max_steps {
+            let mut next = Vec::new();
+            for nd in directions {
+                for i in movement.min_run..=movement.max_run {
                     if let Some((nr, nc)) = Self::move_n(i, nd, r, c, bounds) {
                         let cost = (1..=i)
                             .map(|j| {
@@ -117,28 +157,54 @@ impl Day17 {
                                 grid[nnr][nnc]
                             })
                             .sum::<u32>();
-                        let state = State {
-                            heat_loss: heat_loss + cost,
-                            posdir: (nr, nc, nd),
-                        };
-                        if let Some(&min_heat_loss) = dist.get(&(nr, nc, nd)) {
-                            if state.heat_loss < min_heat_loss {
-                                heap.push(state);
-                                dist.insert((nr, nc, nd), state.heat_loss);
-                            }
-                        } else {
-                            heap.push(state);
-                            dist.insert((nr, nc, nd), state.heat_loss);
-                        }
+                        next.push(((nr, nc, Some(nd)), cost));
                     }
                 }
             }
+            next
+        };
+        let is_goal = |&(r, c, _): &(usize, usize, Option<Direction>)| (r, c) == goal;
+        let result = search::dijkstra(starts, neighbours, is_goal);
+        let heat_loss = result.goal_distance()?;
+        let path = result.goal_path()?;
+
+        let mut steps = Vec::new();
+        for w in path.windows(2) {
+            let ((r, c, _), (nr, nc, Some(d))) = (w[0], w[1]) else {
+                unreachable!("only the start has no direction");
+            };
+            let (mut r, mut c) = (r, c);
+            while (r, c) != (nr, nc) {
+                (r, c) = Self::move_n(1, d, r, c, bounds).unwrap();
+                steps.push(Step {
+                    position: (r, c),
+                    direction: d,
+                    heat_loss: grid[r][c],
+                });
+            }
         }
-        *[Direction::N, Direction::E, Direction::S, Direction::W]
-            .into_iter()
-            .filter_map(|d| dist.get(&(rows - 1, cols - 1, d)))
-            .min()
-            .unwrap()
+        Some(Route { heat_loss, steps })
+    }
+
+    /// The grid with the route drawn over it in arrows, as in the puzzle description.
+    pub fn render(grid: &[Vec<u32>], route: &Route) -> String {
+        let mut cells = grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&h| char::from_digit(h, 10).unwrap_or('?'))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        for step in route.steps.iter() {
+            let (r, c) = step.position;
+            cells[r][c] = step.direction.arrow();
+        }
+        cells
+            .iter()
+            .map(|row| row.iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
     }
 }
 
@@ -154,12 +220,18 @@ impl Solution for Day17 {
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
-        Self::least_heat_loss(grid, 1, 3).to_string()
+        Self::least_heat_loss(grid, &Movement::crucible())
+            .expect("Day 17: the crucible can't reach the factory")
+            .heat_loss
+            .to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
-        Self::least_heat_loss(grid, 4, 10).to_string()
+        Self::least_heat_loss(grid, &Movement::ultra_crucible())
+            .expect("Day 17: the crucible can't reach the factory")
+            .heat_loss
+            .to_string()
     }
 }
 
@@ -190,4 +262,61 @@ mod tests {
     fn check_day17_part2_case1() {
         assert_eq!(Day17::solve_part_two(TEST_INPUT), "94".to_string())
     }
+
+    #[test]
+    fn check_day17_route() {
+        let grid = Day17::parse_input(TEST_INPUT);
+        let route = Day17::least_heat_loss(&grid, &Movement::crucible()).unwrap();
+        assert_eq!(route.heat_loss, 102);
+        assert_eq!(route.steps.iter().map(|s| s.heat_loss).sum::<u32>(), 102);
+        assert_eq!(route.steps.last().unwrap().position, (12, 12));
+        let rendered = Day17::render(&grid, &route);
+        let expected = "2>>34^>>>1323
+32v>>>35v5623
+32552456v>>54
+3446585845v52
+4546657867v>6
+14385987984v4
+44578769877v6
+36378779796v>
+465496798688v
+456467998645v
+12246868655<v
+25465488877v5
+43226746555v>";
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn check_day17_custom_movement() {
+        let grid = Day17::parse_input("19111\n11191\n99991");
+        // a crucible that can only go one block at a time and must turn every block
+        let zigzag = Movement {
+            min_run: 1,
+            max_run: 1,
+            ..Movement::crucible()
+        };
+        let route = Day17::least_heat_loss(&grid, &zigzag).unwrap();
+        assert!(route
+            .steps
+            .windows(2)
+            .all(|w| w[0].direction != w[1].direction));
+
+        // in a single row the only way to stop one block from the start is to overshoot and come back
+        let row = Day17::parse_input("1234");
+        let forward = Movement {
+            min_run: 2,
+            max_run: 3,
+            goal: Some((0, 1)),
+            ..Movement::crucible()
+        };
+        let reversing = Movement {
+            turns: vec![Turn::Left, Turn::Right, Turn::Reverse],
+            ..forward.clone()
+        };
+        let route = Day17::least_heat_loss(&row, &reversing).unwrap();
+        assert_eq!(route.heat_loss, 2 + 3 + 4 + 3 + 2);
+        assert_eq!(route.steps.last().unwrap().position, (0, 1));
+        assert!(Day17::least_heat_loss(&row, &forward).is_none());
+    }
 }
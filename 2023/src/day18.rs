@@ -1,3 +1,4 @@
+use crate::polygon;
 use crate::Solution;
 use itertools::Itertools;
 use std::str::FromStr;
@@ -62,36 +63,9 @@ impl Day18 {
         polygon
     }
 
-    fn boundary(plan: &Vec<(Direction, isize)>) -> isize {
-        plan.iter().map(|&(_, i)| i).sum::<isize>()
-    }
-
-    fn area(polygon: &Vec<(isize, isize)>) -> isize {
-        // https://duckduckgo.com/?q=polygon+area+formula+from+coordinates
-        // https://en.wikipedia.org/wiki/Shoelace_formula
-        (polygon
-            .iter()
-            .cycle()
-            .tuple_windows()
-            .take(polygon.len())
-            .map(|((r, c), (nr, nc))| r * nc - nr * c)
-            .sum::<isize>()
-            / 2)
-        .abs()
-    }
-
-    fn interior(area: isize, boundary: isize) -> isize {
-        // https://duckduckgo.com/?q=polygon+integer+points
-        // https://en.wikipedia.org/wiki/Pick's_theorem
-        area - boundary / 2 + 1
-    }
-
     fn volume(plan: &Vec<(Direction, isize)>) -> isize {
         let polygon = Self::polygon(plan);
-        let boundary = Self::boundary(plan);
-        let area = Self::area(&polygon);
-        let interior = Self::interior(area, boundary);
-        interior + boundary
+        polygon::interior_points(&polygon) + polygon::boundary_points(&polygon)
     }
 }
 
@@ -190,9 +164,7 @@ U 2 (#7a21e3)";
             (Direction::Up, 3),
         ];
         let polygon = Day18::polygon(&plan);
-        let boundary = Day18::boundary(&plan);
-        let area = Day18::area(&polygon);
-        let interior = Day18::interior(area, boundary);
-        assert_eq!(interior, 4)
+        assert_eq!(polygon::boundary_points(&polygon), 22);
+        assert_eq!(polygon::interior_points(&polygon), 4)
     }
 }
@@ -1,46 +1,76 @@
+use crate::intervals::Interval;
+use crate::parse::{self, ParseError, Span};
 use crate::Solution;
 use itertools::Itertools;
-use regex::Regex;
-use std::collections::HashMap;
-use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 pub struct Day19;
 
-#[derive(Copy, Clone, Debug)]
-pub struct Part {
-    x: isize,
-    m: isize,
-    a: isize,
-    s: isize,
+/// The categories parts are rated in, and the ratings possible in each.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Categories {
+    names: Vec<String>,
+    bounds: Vec<Interval<isize>>,
 }
 
-impl Part {
-    fn get_category(&self, category: char) -> isize {
-        match category {
-            'x' => self.x,
-            'm' => self.m,
-            'a' => self.a,
-            's' => self.s,
-            _ => panic!(),
-        }
+impl Default for Categories {
+    fn default() -> Self {
+        Categories::new(["x", "m", "a", "s"])
     }
+}
 
-    fn set_category(&self, category: char, value: isize) -> Part {
-        let mut modified = self.clone();
-        match category {
-            'x' => modified.x = value,
-            'm' => modified.m = value,
-            'a' => modified.a = value,
-            's' => modified.s = value,
-            _ => panic!(),
-        }
-        modified
+impl Categories {
+    /// Categories with ratings from 1 to 4000.
+    pub fn new<'a>(names: impl IntoIterator<Item = &'a str>) -> Self {
+        let names = names
+            .into_iter()
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        let bounds = vec![Interval::new(1, 4000); names.len()];
+        Categories { names, bounds }
     }
+
+    pub fn with_bounds(mut self, name: &str, bounds: Interval<isize>) -> Self {
+        let i = self
+            .index(name)
+            .unwrap_or_else(|| panic!("Day 19: unknown category `{}`", name));
+        self.bounds[i] = bounds;
+        self
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Every part whose ratings are within bounds.
+    pub fn full_range(&self) -> PartRange {
+        self.bounds.clone()
+    }
+}
+
+/// One interval of ratings per category.
+pub type PartRange = Vec<Interval<isize>>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part {
+    /// The rating in each category, in the order of `Categories::names`.
+    pub ratings: Vec<isize>,
+}
+
+pub struct System {
+    pub categories: Categories,
+    pub workflows: HashMap<String, Workflow>,
+    pub parts: Vec<Part>,
 }
 
 #[derive(Debug)]
 pub struct Step {
-    condition: Option<(char, char, isize)>,
+    /// `(category index, '<' or '>', value)`
+    condition: Option<(usize, char, isize)>,
     destination: String,
 }
 
@@ -50,200 +80,499 @@ pub struct Workflow {
     steps: Vec<Step>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
-pub struct ParseDay19Error;
-
-impl FromStr for Part {
-    type Err = ParseDay19Error;
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let number_re = Regex::new(r"[0-9]+").unwrap();
-        let (x, m, a, s) = number_re
-            .find_iter(line)
-            .map(|n| n.as_str().parse::<isize>().unwrap())
-            .collect_tuple()
-            .unwrap();
-        Ok(Part {
-            x: x,
-            m: m,
-            a: a,
-            s: s,
-        })
+/// Workflows compiled into a binary tree of comparisons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Accept,
+    Reject,
+    /// Go to `then` if the part's rating in `category` is below `threshold`.
+    Below {
+        category: usize,
+        threshold: isize,
+        then: Box<Decision>,
+        otherwise: Box<Decision>,
+    },
+}
+
+impl Decision {
+    pub fn decide(&self, part: &Part) -> bool {
+        match self {
+            Decision::Accept => true,
+            Decision::Reject => false,
+            Decision::Below {
+                category,
+                threshold,
+                then,
+                otherwise,
+            } => {
+                if part.ratings[*category] < *threshold {
+                    then.decide(part)
+                } else {
+                    otherwise.decide(part)
+                }
+            }
+        }
+    }
+
+    /// The accepted parts within `range`, as disjoint hyper-rectangles.
+    pub fn accepted(&self, range: PartRange) -> Vec<PartRange> {
+        match self {
+            Decision::Accept => vec![range],
+            Decision::Reject => vec![],
+            Decision::Below {
+                category,
+                threshold,
+                then,
+                otherwise,
+            } => {
+                let (below, above) = Day19::split_range(range, *category, *threshold);
+                let mut accepted = below.map_or(vec![], |below| then.accepted(below));
+                accepted.extend(above.map_or(vec![], |above| otherwise.accepted(above)));
+                accepted
+            }
+        }
+    }
+
+    /// Drop comparisons that always go the same way for parts in `range`,
+    /// or that lead to the same outcome either way.
+    pub fn simplify(&self, range: PartRange) -> Decision {
+        let Decision::Below {
+            category,
+            threshold,
+            then,
+            otherwise,
+        } = self
+        else {
+            return self.clone();
+        };
+        match Day19::split_range(range, *category, *threshold) {
+            (Some(below), None) => then.simplify(below),
+            (None, Some(above)) => otherwise.simplify(above),
+            (Some(below), Some(above)) => {
+                let (then, otherwise) = (then.simplify(below), otherwise.simplify(above));
+                if then == otherwise {
+                    then
+                } else {
+                    Decision::Below {
+                        category: *category,
+                        threshold: *threshold,
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    }
+                }
+            }
+            (None, None) => unreachable!("ranges are never empty"),
+        }
     }
 }
 
-impl FromStr for Step {
-    type Err = ParseDay19Error;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some((cond_str, dest)) = s.split_once(":") {
-            Ok(Step {
-                condition: Some((
-                    cond_str.chars().nth(0).unwrap(),
-                    cond_str.chars().nth(1).unwrap(),
-                    cond_str[2..].parse::<isize>().unwrap(),
-                )),
-                destination: dest.to_string(),
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompileError {
+    MissingWorkflow(String),
+    /// The workflow's last rule has a condition, so parts can fall off the end.
+    NoFallback(String),
+    Cycle(Vec<String>),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Redundancy {
+    /// Every part reaching the rule meets its condition.
+    AlwaysTrue,
+    /// No part reaching the rule meets its condition.
+    NeverTrue,
+    /// The rule sends parts to the same place as the rules after it.
+    SameAsFallthrough,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// `(workflow, rule index)` for rules no part reaches, in workflows that are reached.
+    pub unreachable_rules: Vec<(String, usize)>,
+    pub unreachable_workflows: Vec<String>,
+    pub redundant_conditions: Vec<(String, usize, Redundancy)>,
+    pub cycles: Vec<Vec<String>>,
+    /// The parts each workflow accepts, for workflows that don't lead into a cycle.
+    pub accepted_regions: BTreeMap<String, Vec<PartRange>>,
+}
+
+impl Part {
+    fn parse(span: Span, categories: &Categories) -> Result<Part, ParseError> {
+        let (_, items) = parse::record(span)?;
+        let mut ratings = vec![None; categories.names().len()];
+        for item in items {
+            let (category, rating) = item.split_once("=")?;
+            let i = categories
+                .index(category.text)
+                .ok_or_else(|| category.error(format!("unknown category `{}`", category.text)))?;
+            if ratings[i].is_some() {
+                return Err(category.error(format!("repeated category `{}`", category.text)));
+            }
+            ratings[i] = Some(rating.parse::<isize>()?);
+        }
+        let ratings = ratings
+            .into_iter()
+            .zip(categories.names())
+            .map(|(rating, name)| {
+                rating.ok_or_else(|| span.error(format!("missing category `{}`", name)))
             })
-        } else {
-            Ok(Step {
+            .collect::<Result<_, _>>()?;
+        Ok(Part { ratings })
+    }
+}
+
+impl Step {
+    fn parse(span: Span, categories: &Categories) -> Result<Step, ParseError> {
+        let Ok((condition, destination)) = span.split_once(":") else {
+            return Ok(Step {
                 condition: None,
-                destination: s.to_string(),
-            })
-        }
+                destination: span.text.to_string(),
+            });
+        };
+        let Some(at) = condition.text.find(['<', '>']) else {
+            return Err(condition.error("expected a condition like `a<2006`"));
+        };
+        let category = condition.of(&condition.text[..at]);
+        let i = categories
+            .index(category.text)
+            .ok_or_else(|| category.error(format!("unknown category `{}`", category.text)))?;
+        let compare_op = condition.text[at..].chars().next().unwrap();
+        let value = condition.of(&condition.text[at + 1..]).parse::<isize>()?;
+        Ok(Step {
+            condition: Some((i, compare_op, value)),
+            destination: destination.text.to_string(),
+        })
     }
 }
 
-impl FromStr for Workflow {
-    type Err = ParseDay19Error;
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let (name, steps_str, _) = line.split(&['{', '}']).collect_tuple().unwrap();
-        let steps = steps_str
-            .split(',')
-            .map(|step_str| step_str.parse::<Step>().unwrap())
-            .collect();
+impl Workflow {
+    fn parse(span: Span, categories: &Categories) -> Result<Workflow, ParseError> {
+        let (name, items) = parse::record(span)?;
         Ok(Workflow {
-            name: name.to_string(),
-            steps: steps,
+            name: name.text.to_string(),
+            steps: items
+                .into_iter()
+                .map(|item| Step::parse(item, categories))
+                .collect::<Result<_, _>>()?,
         })
     }
 }
 
 impl Day19 {
-    fn apply_condition(part: Part, condition: Option<(char, char, isize)>) -> bool {
-        if let Some((category, compare_op, value)) = condition {
-            let rating = part.get_category(category);
-            return match compare_op {
-                '>' => rating > value,
-                '<' => rating < value,
-                _ => panic!(),
-            };
-        }
-        return true;
+    /// Parse workflows and parts, rated in the categories named by the first part,
+    /// or in the puzzle's `x`, `m`, `a` and `s` if there are no parts.
+    fn parse_system(input: Span) -> Result<System, ParseError> {
+        let blocks = input.blocks();
+        let (workflows, parts) = match blocks[..] {
+            [workflows] => (workflows, None),
+            [workflows, parts] => (workflows, Some(parts)),
+            _ => return Err(input.error("expected workflows and parts separated by a blank line")),
+        };
+        let categories = match parts.and_then(|parts| parts.lines().next()) {
+            Some(first) => Categories::new(
+                parse::record(first)?
+                    .1
+                    .iter()
+                    .map(|item| item.text.split('=').next().unwrap()),
+            ),
+            None => Categories::default(),
+        };
+        let workflows = parse::lines(workflows, |line| Workflow::parse(line, &categories))?
+            .into_iter()
+            .map(|w| (w.name.to_string(), w))
+            .collect();
+        let parts = match parts {
+            Some(parts) => parse::lines(parts, |line| Part::parse(line, &categories))?,
+            None => Vec::new(),
+        };
+        Ok(System {
+            categories,
+            workflows,
+            parts,
+        })
     }
 
-    fn process_part(workflows: &HashMap<String, Workflow>, part: Part, start: String) -> bool {
-        let mut state = &start;
-        loop {
-            let steps = &workflows.get(state).unwrap().steps;
-            for step in steps.iter() {
-                if Self::apply_condition(part, step.condition) {
-                    state = &step.destination;
-                    if state == "A" {
-                        return true;
-                    } else if state == "R" {
-                        return false;
-                    }
-                    break;
-                }
-            }
-        }
+    /// Split into the parts rating below `threshold` in `category`, and the rest.
+    fn split_range(
+        part_range: PartRange,
+        category: usize,
+        threshold: isize,
+    ) -> (Option<PartRange>, Option<PartRange>) {
+        let (lo, hi) = part_range[category].split_at(threshold);
+        let with = |interval: Option<Interval<isize>>| {
+            interval.map(|interval| {
+                let mut range = part_range.clone();
+                range[category] = interval;
+                range
+            })
+        };
+        (with(lo), with(hi))
     }
 
     fn apply_condition_to_range(
-        part_range: (Part, Part),
-        condition: Option<(char, char, isize)>,
-    ) -> (Option<(Part, Part)>, Option<(Part, Part)>) {
+        part_range: PartRange,
+        condition: Option<(usize, char, isize)>,
+    ) -> (Option<PartRange>, Option<PartRange>) {
         let Some((category, compare_op, value)) = condition else {
             return (Some(part_range), None);
         };
-        let mid = if compare_op == '>' { value + 1 } else { value };
-        let (lo, hi) = part_range;
-
-        // really not sure why i can get away with this
-        assert!(mid - 1 <= hi.get_category(category));
-        assert!(mid >= lo.get_category(category));
-        assert!(lo.get_category(category) <= mid - 1);
-        assert!(mid <= hi.get_category(category));
-
-        let lo_range = Some((lo, hi.set_category(category, mid - 1)));
-        let hi_range = Some((lo.set_category(category, mid), hi));
         if compare_op == '>' {
-            (hi_range, lo_range)
+            let Some(threshold) = value.checked_add(1) else {
+                return (None, Some(part_range));
+            };
+            let (lo, hi) = Self::split_range(part_range, category, threshold);
+            (hi, lo)
         } else {
-            (lo_range, hi_range)
+            Self::split_range(part_range, category, value)
         }
     }
 
-    fn accepted_part_ranges(
+    fn compile_from(
         workflows: &HashMap<String, Workflow>,
-        part_range: (Part, Part),
-        start: String,
-    ) -> Vec<(Part, Part)> {
-        let mut to_process = Vec::from([(&start, part_range)]);
-        let mut accepted_ranges = Vec::new();
-        while let Some((state, mut range)) = to_process.pop() {
-            let steps = &workflows.get(state).unwrap().steps;
-            for step in steps.iter() {
+        name: &str,
+        path: &mut Vec<String>,
+        compiled: &mut HashMap<String, Decision>,
+    ) -> Result<Decision, CompileError> {
+        match name {
+            "A" => return Ok(Decision::Accept),
+            "R" => return Ok(Decision::Reject),
+            _ => {}
+        }
+        if let Some(decision) = compiled.get(name) {
+            return Ok(decision.clone());
+        }
+        if let Some(i) = path.iter().position(|n| n == name) {
+            return Err(CompileError::Cycle(path[i..].to_vec()));
+        }
+        let workflow = workflows
+            .get(name)
+            .ok_or_else(|| CompileError::MissingWorkflow(name.to_string()))?;
+        path.push(name.to_string());
+        // build from the last rule backwards, so each condition knows what happens otherwise
+        let mut rest = None;
+        for step in workflow.steps.iter().rev() {
+            let target = Self::compile_from(workflows, &step.destination, path, compiled)?;
+            rest = Some(match step.condition {
+                None => target,
+                Some((category, compare_op, value)) => {
+                    let rest = rest.ok_or_else(|| CompileError::NoFallback(name.to_string()))?;
+                    let (then, otherwise, threshold) = match value.checked_add(1) {
+                        _ if compare_op == '<' => (target, rest, value),
+                        Some(threshold) => (rest, target, threshold),
+                        // nothing rates above the largest value
+                        None => (rest.clone(), rest, value),
+                    };
+                    Decision::Below {
+                        category,
+                        threshold,
+                        then: Box::new(then),
+                        otherwise: Box::new(otherwise),
+                    }
+                }
+            });
+        }
+        path.pop();
+        let decision = rest.ok_or_else(|| CompileError::NoFallback(name.to_string()))?;
+        compiled.insert(name.to_string(), decision.clone());
+        Ok(decision)
+    }
+
+    /// Compile the workflows, starting from `start`, into a single decision tree.
+    pub fn compile(
+        workflows: &HashMap<String, Workflow>,
+        start: &str,
+    ) -> Result<Decision, CompileError> {
+        Self::compile_from(workflows, start, &mut Vec::new(), &mut HashMap::new())
+    }
+
+    /// Cycles in the graph of workflows sending parts to each other.
+    fn cycles(workflows: &HashMap<String, Workflow>) -> Vec<Vec<String>> {
+        fn visit<'a>(
+            workflows: &'a HashMap<String, Workflow>,
+            name: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+            cycles: &mut Vec<Vec<String>>,
+        ) {
+            if let Some(i) = path.iter().position(|&n| n == name) {
+                cycles.push(path[i..].iter().map(|n| n.to_string()).collect());
+                return;
+            }
+            let Some(workflow) = workflows.get(name) else {
+                return;
+            };
+            if !done.insert(name) {
+                return;
+            }
+            path.push(name);
+            for step in workflow.steps.iter() {
+                visit(workflows, &step.destination, path, done, cycles);
+            }
+            path.pop();
+        }
+        let mut cycles = Vec::new();
+        let mut done = HashSet::new();
+        for name in workflows.keys().sorted() {
+            visit(workflows, name, &mut Vec::new(), &mut done, &mut cycles);
+        }
+        cycles
+    }
+
+    /// Find rules and workflows that no part from `in` ever reaches,
+    /// conditions that don't affect the outcome, cycles between workflows,
+    /// and the parts each workflow accepts.
+    pub fn analyze(workflows: &HashMap<String, Workflow>, part_range: PartRange) -> Report {
+        // (reached, sometimes true, sometimes false) for every rule
+        let mut outcomes: HashMap<(&str, usize), (bool, bool, bool)> = HashMap::new();
+        let mut seen = HashSet::new();
+        let mut to_process = vec![("in", part_range.clone())];
+        while let Some((name, mut range)) = to_process.pop() {
+            let Some(workflow) = workflows.get(name) else {
+                continue;
+            };
+            if !seen.insert((name, range.clone())) {
+                continue;
+            }
+            for (i, step) in workflow.steps.iter().enumerate() {
                 let (going, staying) = Self::apply_condition_to_range(range, step.condition);
+                let outcome = outcomes.entry((name, i)).or_default();
+                *outcome = (
+                    true,
+                    outcome.1 || going.is_some(),
+                    outcome.2 || staying.is_some(),
+                );
                 if let Some(next_range) = going {
-                    let next_state = &step.destination;
-                    if next_state == "A" {
-                        accepted_ranges.push(next_range);
-                    } else if next_state != "R" {
-                        to_process.push((next_state, next_range));
-                    }
+                    to_process.push((&step.destination, next_range));
                 }
-                if let Some(next_range) = staying {
-                    range = next_range;
-                } else {
+                let Some(next_range) = staying else {
                     break;
+                };
+                range = next_range;
+            }
+        }
+
+        let mut report = Report {
+            cycles: Self::cycles(workflows),
+            ..Report::default()
+        };
+        for (name, workflow) in workflows.iter().sorted_by_key(|&(name, _)| name) {
+            if !outcomes.contains_key(&(name.as_str(), 0)) {
+                report.unreachable_workflows.push(name.to_string());
+            }
+            for (i, step) in workflow.steps.iter().enumerate() {
+                let redundancy = match outcomes.get(&(name.as_str(), i)) {
+                    None => {
+                        if !report.unreachable_workflows.contains(name) {
+                            report.unreachable_rules.push((name.to_string(), i));
+                        }
+                        continue;
+                    }
+                    _ if step.condition.is_none() => continue,
+                    Some((_, _, false)) => Redundancy::AlwaysTrue,
+                    Some((_, false, _)) => Redundancy::NeverTrue,
+                    _ if workflow.steps[i..]
+                        .iter()
+                        .all(|later| later.destination == step.destination) =>
+                    {
+                        Redundancy::SameAsFallthrough
+                    }
+                    _ => continue,
+                };
+                report
+                    .redundant_conditions
+                    .push((name.to_string(), i, redundancy));
+            }
+            if let Ok(decision) = Self::compile(workflows, name) {
+                report.accepted_regions.insert(
+                    name.to_string(),
+                    decision
+                        .simplify(part_range.clone())
+                        .accepted(part_range.clone()),
+                );
+            }
+        }
+        report
+    }
+
+    /// Write a decision tree back out as workflows, starting from `in`.
+    pub fn export(decision: &Decision, categories: &Categories) -> String {
+        let mut names = (0..).map(|mut i: usize| {
+            // a, b, ..., z, aa, ab, ...
+            let mut name = String::new();
+            loop {
+                name.insert(0, (b'a' + (i % 26) as u8) as char);
+                if i < 26 {
+                    return name;
                 }
+                i = i / 26 - 1;
             }
+        });
+        let mut lines = Vec::new();
+        let mut to_export = VecDeque::from([("in".to_string(), decision)]);
+        while let Some((name, mut node)) = to_export.pop_front() {
+            let mut rules = Vec::new();
+            while let Decision::Below {
+                category,
+                threshold,
+                then,
+                otherwise,
+            } = node
+            {
+                let target = match then.as_ref() {
+                    Decision::Accept => "A".to_string(),
+                    Decision::Reject => "R".to_string(),
+                    _ => {
+                        let next = names.find(|n| n != "in").unwrap();
+                        to_export.push_back((next.to_string(), then));
+                        next
+                    }
+                };
+                rules.push(format!(
+                    "{}<{}:{}",
+                    categories.names()[*category],
+                    threshold,
+                    target
+                ));
+                node = otherwise;
+            }
+            rules.push(if *node == Decision::Accept { "A" } else { "R" }.to_string());
+            lines.push(format!("{}{{{}}}", name, rules.join(",")));
         }
-        accepted_ranges
+        lines.join("\n")
     }
 }
 
 impl Solution for Day19 {
-    type ParsedInput = (HashMap<String, Workflow>, Vec<Part>);
+    type ParsedInput = System;
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        let (workflows_str, parts_str) = input_lines.split_once("\n\n").unwrap();
-        let workflows = workflows_str
-            .lines()
-            .map(|l| l.parse::<Workflow>().unwrap())
-            .map(|w| (w.name.to_string(), w))
-            .collect::<HashMap<String, Workflow>>();
-        let parts = parts_str
-            .lines()
-            .map(|l| l.parse::<Part>().unwrap())
-            .collect();
-        (workflows, parts)
+        Self::parse_system(Span::new(input_lines)).unwrap_or_else(|e| panic!("Day 19: {}", e))
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
-        let (workflows, parts) = _parsed_input;
-        parts
+        let system = _parsed_input;
+        let decision =
+            Self::compile(&system.workflows, "in").unwrap_or_else(|e| panic!("Day 19: {:?}", e));
+        system
+            .parts
             .iter()
-            .filter(|&part| Self::process_part(&workflows, *part, "in".to_string()))
-            .map(|part| part.x + part.m + part.a + part.s)
+            .filter(|&part| decision.decide(part))
+            .map(|part| part.ratings.iter().sum::<isize>())
             .sum::<isize>()
             .to_string()
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
-        let (workflows, _) = _parsed_input;
-        let start = "in".to_string();
-        let part_range = (
-            Part {
-                x: 1,
-                m: 1,
-                a: 1,
-                s: 1,
-            },
-            Part {
-                x: 4000,
-                m: 4000,
-                a: 4000,
-                s: 4000,
-            },
-        );
-        Self::accepted_part_ranges(&workflows, part_range, start)
+        let system = _parsed_input;
+        let decision =
+            Self::compile(&system.workflows, "in").unwrap_or_else(|e| panic!("Day 19: {:?}", e));
+        decision
+            .accepted(system.categories.full_range())
             .iter()
-            .map(|&(min_part, max_part)| {
-                (max_part.x - min_part.x + 1)
-                    * (max_part.m - min_part.m + 1)
-                    * (max_part.a - min_part.a + 1)
-                    * (max_part.s - min_part.s + 1)
+            .map(|range| {
+                range
+                    .iter()
+                    .map(|interval| interval.len())
+                    .product::<isize>()
             })
             .sum::<isize>()
             .to_string()
@@ -284,4 +613,124 @@ hdj{m>838:A,pv}
             "167409079868000".to_string()
         )
     }
+
+    #[test]
+    fn check_day19_parse_error() {
+        let input = TEST_INPUT.replace("{x=2036,m=264", "{x=2036,q=264");
+        let error = Day19::parse_system(Span::new(&input)).err().unwrap();
+        assert_eq!((error.line, error.column), (15, 9));
+        assert_eq!(error.message, "unknown category `q`");
+    }
+
+    #[test]
+    fn check_day19_export() {
+        let system = Day19::parse_input(TEST_INPUT);
+        let decision = Day19::compile(&system.workflows, "in").unwrap();
+        let simplified = decision.simplify(system.categories.full_range());
+        assert!(system
+            .parts
+            .iter()
+            .all(|part| decision.decide(part) == simplified.decide(part)));
+        let exported = Day19::export(&simplified, &system.categories);
+        assert!(exported.starts_with("in{s<1351:a,"));
+        // lnx{m>1548:A,A} always accepts
+        assert!(!exported.contains("m<1549"));
+        let parts = TEST_INPUT.split_once("\n\n").unwrap().1;
+        let input = format!("{}\n\n{}", exported, parts);
+        assert_eq!(Day19::solve_part_one(&input), "19114".to_string());
+        assert_eq!(Day19::solve_part_two(&input), "167409079868000".to_string());
+    }
+
+    #[test]
+    fn check_day19_analyze() {
+        let system = Day19::parse_input(TEST_INPUT);
+        let full = system.categories.full_range();
+        let report = Day19::analyze(&system.workflows, full.clone());
+        assert_eq!(
+            report.redundant_conditions,
+            vec![
+                ("gd".to_string(), 0, Redundancy::SameAsFallthrough),
+                ("lnx".to_string(), 0, Redundancy::SameAsFallthrough),
+            ]
+        );
+        assert!(report.unreachable_rules.is_empty() && report.cycles.is_empty());
+        assert!(report.accepted_regions["gd"].is_empty());
+        assert_eq!(report.accepted_regions["lnx"], vec![full.clone()]);
+
+        let workflows = Day19::parse_input(
+            "in{x<10:a,x<5:R,c}
+a{x>20:R,A}
+c{x<4000:A,x>1:R,A}
+b{A}
+d{x<5:e,A}
+e{d}
+
+{x=1,m=1,a=1,s=1}",
+        )
+        .workflows;
+        let report = Day19::analyze(&workflows, full.clone());
+        assert_eq!(report.unreachable_workflows, vec!["b", "d", "e"]);
+        assert_eq!(report.unreachable_rules, vec![("c".to_string(), 2)]);
+        assert_eq!(
+            report.redundant_conditions,
+            vec![
+                ("a".to_string(), 0, Redundancy::NeverTrue),
+                ("c".to_string(), 1, Redundancy::AlwaysTrue),
+                ("in".to_string(), 1, Redundancy::NeverTrue),
+            ]
+        );
+        assert_eq!(report.cycles, vec![vec!["d".to_string(), "e".to_string()]]);
+        assert!(!report.accepted_regions.contains_key("d"));
+        assert_eq!(
+            Day19::compile(&workflows, "e"),
+            Err(CompileError::Cycle(vec!["e".to_string(), "d".to_string()]))
+        );
+        let mut x = full;
+        x[0] = Interval::new(1, 9);
+        assert_eq!(report.accepted_regions["in"][0], x);
+    }
+
+    #[test]
+    fn check_day19_categories() {
+        let input = "in{speed>5:fast,colour<3:R,A}
+fast{colour>2:A,speed>9223372036854775807:R,weight<-7:R,A}
+
+{speed=7,colour=1,weight=10}
+{colour=4,weight=0,speed=2}
+{weight=1,speed=6,colour=0}";
+        let system = Day19::parse_input(input);
+        assert_eq!(system.categories.names(), ["speed", "colour", "weight"]);
+        assert_eq!(system.parts[1].ratings, vec![2, 4, 0]);
+        assert_eq!(Day19::solve_part_one(input), "31".to_string());
+
+        let decision = Day19::compile(&system.workflows, "in").unwrap();
+        let categories = system
+            .categories
+            .with_bounds("speed", Interval::new(0, 9))
+            .with_bounds("colour", Interval::new(0, 4))
+            .with_bounds("weight", Interval::new(1, 2));
+        // slow parts need colour >= 3, fast ones are all accepted
+        let count = |range: &PartRange| range.iter().map(|i| i.len()).product::<isize>();
+        let accepted = decision.accepted(categories.full_range());
+        assert_eq!(
+            accepted.iter().map(count).sum::<isize>(),
+            6 * 2 * 2 + 4 * 5 * 2
+        );
+        // thresholds outside the bounds never split anything
+        let simplified = decision.simplify(categories.full_range());
+        let exported = Day19::export(&simplified, &categories);
+        assert_eq!(exported, "in{speed<6:a,A}\na{colour<3:R,A}");
+
+        let error = Day19::parse_system(Span::new("in{size<3:A,R}\n\n{x=1}"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            (error.line, error.column, error.message.as_str()),
+            (1, 4, "unknown category `size`")
+        );
+        let error = Day19::parse_system(Span::new("in{x<3:A,R}\n\n{x=1,y=2}\n{y=1}"))
+            .err()
+            .unwrap();
+        assert_eq!(error.message, "missing category `x`");
+    }
 }
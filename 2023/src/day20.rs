@@ -1,6 +1,9 @@
+use crate::cycles::{self, Cycle};
 use crate::Solution;
-use num::integer::lcm;
-use std::collections::{HashMap, VecDeque};
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::{self, Write as _};
+use std::io::{self, BufRead, Write};
 
 pub struct Day20;
 
@@ -11,72 +14,599 @@ pub enum ModuleState {
     Conjunction { inputs: HashMap<String, bool> },
 }
 
+/// A pulse as `(high, from, to)`. The button is the empty name.
+pub type Pulse = (bool, String, String);
+
+pub fn format_pulse((high, from, to): &Pulse) -> String {
+    let from = if from.is_empty() { "button" } else { from };
+    let level = if *high { "high" } else { "low" };
+    format!("{} -{}-> {}", from, level, to)
+}
+
+/// Every module's state, one per line in name order.
+pub fn format_states(module_states: &HashMap<String, ModuleState>) -> String {
+    let mut out = String::new();
+    for (name, state) in module_states.iter().sorted_by_key(|&(name, _)| name) {
+        let line = match state {
+            ModuleState::Broadcast => name.to_string(),
+            ModuleState::FlipFlop { on } => {
+                format!("%{} {}", name, if *on { "on" } else { "off" })
+            }
+            ModuleState::Conjunction { inputs } => format!(
+                "&{} {}",
+                name,
+                inputs
+                    .iter()
+                    .sorted()
+                    .map(|(input, &high)| format!(
+                        "{}={}",
+                        input,
+                        if high { "high" } else { "low" }
+                    ))
+                    .join(" ")
+            ),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+/// A saved copy of every module's state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot(HashMap<String, ModuleState>);
+
+impl Snapshot {
+    pub fn take(module_states: &HashMap<String, ModuleState>) -> Self {
+        Snapshot(module_states.clone())
+    }
+
+    pub fn restore(&self, module_states: &mut HashMap<String, ModuleState>) {
+        module_states.clone_from(&self.0);
+    }
+}
+
+/// Delivers the pulses of each button press one at a time.
+pub struct Stepper<'a> {
+    pub module_states: &'a mut HashMap<String, ModuleState>,
+    module_dests: &'a HashMap<String, Vec<String>>,
+    queue: VecDeque<Pulse>,
+    pub presses: usize,
+    saved: Option<(Snapshot, VecDeque<Pulse>, usize)>,
+}
+
+impl<'a> Stepper<'a> {
+    pub fn new(
+        module_states: &'a mut HashMap<String, ModuleState>,
+        module_dests: &'a HashMap<String, Vec<String>>,
+    ) -> Self {
+        Stepper {
+            module_states,
+            module_dests,
+            queue: VecDeque::new(),
+            presses: 0,
+            saved: None,
+        }
+    }
+
+    /// Whether the last press still has pulses to deliver.
+    pub fn is_busy(&self) -> bool {
+        !self.queue.is_empty()
+    }
+
+    pub fn press(&mut self) {
+        self.presses += 1;
+        self.queue
+            .push_back((false, "".to_string(), "broadcaster".to_string()));
+    }
+
+    /// Deliver the next pulse, queueing any pulses it causes, and return it.
+    pub fn step(&mut self) -> Option<Pulse> {
+        let (pulse, from, current) = self.queue.pop_front()?;
+        let delivered = (pulse, from.to_string(), current.to_string());
+        let (Some(state), Some(dests)) = (
+            self.module_states.get_mut(&current),
+            self.module_dests.get(&current),
+        ) else {
+            return Some(delivered);
+        };
+        let new_pulse = match state {
+            ModuleState::Broadcast => pulse,
+            ModuleState::FlipFlop { ref mut on } => {
+                if pulse {
+                    return Some(delivered);
+                }
+                *on = !*on;
+                *on
+            }
+            ModuleState::Conjunction { ref mut inputs } => {
+                inputs.insert(from, pulse);
+                !inputs.values().all(|&b| b)
+            }
+        };
+        for dest in dests.iter() {
+            self.queue
+                .push_back((new_pulse, current.to_string(), dest.to_string()));
+        }
+        Some(delivered)
+    }
+
+    /// Save the module states along with any pulses still to be delivered.
+    pub fn save(&mut self) {
+        self.saved = Some((
+            Snapshot::take(self.module_states),
+            self.queue.clone(),
+            self.presses,
+        ));
+    }
+
+    /// Go back to the last save, returning whether there was one.
+    pub fn restore(&mut self) -> bool {
+        let Some((snapshot, queue, presses)) = &self.saved else {
+            return false;
+        };
+        snapshot.restore(self.module_states);
+        self.queue.clone_from(queue);
+        self.presses = *presses;
+        true
+    }
+}
+
+/// Which pulses to keep in a trace. Empty `modules` keeps pulses between any modules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    pub modules: Vec<String>,
+    pub level: Option<bool>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, (high, from, to): &Pulse) -> bool {
+        (self.modules.is_empty() || self.modules.contains(from) || self.modules.contains(to))
+            && self.level.is_none_or(|level| level == *high)
+    }
+}
+
+/// A way the network breaks the assumptions `Day20::analyze` relies on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssumptionFailed {
+    /// Nothing sends pulses to the target.
+    NoFeeder(String),
+    /// More than one module sends pulses to the target.
+    SeveralFeeders(String, Vec<String>),
+    /// The module feeding the target isn't a conjunction.
+    FeederNotConjunction(String),
+    /// Two inputs of the feeder both depend on a module other than the broadcaster.
+    SharedModule(String, String, String),
+    /// An input of the feeder never sends a high pulse.
+    NeverFires(String),
+    /// The inputs of the feeder never all send a high pulse on the same press.
+    NoCommonPress,
+}
+
+impl fmt::Display for AssumptionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AssumptionFailed::NoFeeder(target) => write!(f, "nothing sends pulses to {}", target),
+            AssumptionFailed::SeveralFeeders(target, feeders) => {
+                write!(f, "{} is fed by {}", target, feeders.join(", "))
+            }
+            AssumptionFailed::FeederNotConjunction(feeder) => {
+                write!(f, "{} is not a conjunction", feeder)
+            }
+            AssumptionFailed::SharedModule(a, b, module) => {
+                write!(f, "{} and {} both depend on {}", a, b, module)
+            }
+            AssumptionFailed::NeverFires(input) => {
+                write!(f, "{} never sends a high pulse", input)
+            }
+            AssumptionFailed::NoCommonPress => {
+                write!(f, "the feeder's inputs never fire on the same press")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoding {
+    /// A chain of flip-flops, lowest bit first, counting up to `value` and then resetting.
+    Counter { bits: Vec<String>, value: usize },
+    /// Found by simulating the subcircuit until its state repeats.
+    Simulated,
+}
+
+/// The modules one input of the feeder depends on, and the presses on which it fires.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subcircuit {
+    pub output: String,
+    pub modules: BTreeSet<String>,
+    pub cycle: Cycle,
+    /// Presses up to `cycle.prefix + cycle.period` on which `output` sends a high pulse.
+    pub presses: Vec<usize>,
+    pub decoding: Decoding,
+}
+
+impl Subcircuit {
+    /// Whether `output` sends a high pulse on press `n`, counting from 1.
+    pub fn fires_on(&self, n: usize) -> bool {
+        // press n acts on the state after n - 1 presses
+        let n = self.cycle.reduce(n - 1) + 1;
+        self.presses.contains(&n)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    pub feeder: String,
+    pub subcircuits: Vec<Subcircuit>,
+}
+
+impl Analysis {
+    /// The first press on which every input of the feeder sends a high pulse,
+    /// so that the feeder sends a low pulse to the target.
+    pub fn first_press(&self) -> Result<usize, AssumptionFailed> {
+        let transient = self
+            .subcircuits
+            .iter()
+            .map(|sub| sub.cycle.prefix)
+            .max()
+            .unwrap_or(0);
+        if let Some(n) =
+            (1..=transient).find(|&n| self.subcircuits.iter().all(|sub| sub.fires_on(n)))
+        {
+            return Ok(n);
+        }
+        // past every prefix, each subcircuit fires on a fixed set of residues
+        self.subcircuits
+            .iter()
+            .map(|sub| {
+                let period = sub.cycle.period as i128;
+                sub.presses
+                    .iter()
+                    .filter(|&&n| n > sub.cycle.prefix)
+                    .map(move |&n| (n as i128 % period, period))
+                    .collect::<Vec<_>>()
+            })
+            .multi_cartesian_product()
+            .filter_map(|congruences| cycles::crt(&congruences))
+            .map(|(x, lcm)| {
+                // the first solution after every prefix
+                let after = transient as i128 + 1;
+                x + ((after - x).max(0) + lcm - 1) / lcm * lcm
+            })
+            .min()
+            .map(|n| n as usize)
+            .ok_or(AssumptionFailed::NoCommonPress)
+    }
+}
+
 impl Day20 {
     fn press_button(
         module_states: &mut HashMap<String, ModuleState>,
         module_dests: &HashMap<String, Vec<String>>,
-    ) -> Vec<(bool, String, String)> {
+    ) -> Vec<Pulse> {
+        let mut stepper = Stepper::new(module_states, module_dests);
+        stepper.press();
+        std::iter::from_fn(|| stepper.step()).collect()
+    }
+
+    fn simulate(
+        module_states: &mut HashMap<String, ModuleState>,
+        module_dests: &HashMap<String, Vec<String>>,
+        presses: usize,
+    ) -> Vec<Pulse> {
         let mut sent = Vec::new();
-        let mut queue = VecDeque::from([(false, "".to_string(), "broadcaster".to_string())]);
-        sent.push((false, "".to_string(), "broadcaster".to_string()));
-        while let Some((pulse, from, current)) = queue.pop_front() {
-            let Some(state) = module_states.get_mut(&current) else {
-                continue;
-            };
-            let Some(dests) = module_dests.get(&current) else {
-                continue;
-            };
-            let new_pulse = match state {
-                ModuleState::Broadcast => pulse,
-                ModuleState::FlipFlop { ref mut on } => {
-                    if pulse {
-                        continue;
+        for _ in 0..presses {
+            sent.append(&mut Self::press_button(module_states, module_dests));
+        }
+        sent
+    }
+
+    fn upstream(module_dests: &HashMap<String, Vec<String>>, module: &str) -> HashSet<String> {
+        let mut seen = HashSet::from([module.to_string()]);
+        let mut stack = vec![module.to_string()];
+        while let Some(current) = stack.pop() {
+            for (input, dests) in module_dests.iter() {
+                if dests.contains(&current) && seen.insert(input.to_string()) {
+                    stack.push(input.to_string());
+                }
+            }
+        }
+        seen
+    }
+
+    /// Detect the period of the subcircuit feeding `module`,
+    /// and the presses (before its state first repeats) on which `module` sends a high pulse.
+    fn firing_cycle(
+        module_states: &HashMap<String, ModuleState>,
+        module_dests: &HashMap<String, Vec<String>>,
+        module: &str,
+    ) -> (Cycle, Vec<usize>) {
+        // modules outside the subcircuit can't affect it, so leave them out of the state
+        let upstream = Self::upstream(module_dests, module);
+        let start = module_states
+            .iter()
+            .filter(|&(name, _)| upstream.contains(name))
+            .map(|(name, state)| (name.to_string(), state.clone()))
+            .collect::<HashMap<String, ModuleState>>();
+        let press = |states: &HashMap<String, ModuleState>| {
+            let mut states = states.clone();
+            Self::press_button(&mut states, module_dests);
+            states
+        };
+        let cycle = cycles::detect_unhashed(&start, press);
+        let mut states = start;
+        let presses = (1..=cycle.prefix + cycle.period)
+            .filter(|_| {
+                Self::press_button(&mut states, module_dests)
+                    .iter()
+                    .any(|(p, from, _)| from == module && *p)
+            })
+            .collect();
+        (cycle, presses)
+    }
+
+    /// Recognise `output` as an inverter on a binary counter: a chain of flip-flops
+    /// started by the broadcaster, with a conjunction watching the bits that are set
+    /// in its target value and resetting the chain once it gets there.
+    fn decode_counter(
+        module_states: &HashMap<String, ModuleState>,
+        module_dests: &HashMap<String, Vec<String>>,
+        output: &str,
+    ) -> Option<(Vec<String>, usize)> {
+        let Some(ModuleState::Conjunction { inputs }) = module_states.get(output) else {
+            return None;
+        };
+        let (counter,) = inputs.keys().collect_tuple()?;
+        let Some(ModuleState::Conjunction { inputs: watched }) = module_states.get(counter) else {
+            return None;
+        };
+        let upstream = Self::upstream(module_dests, counter);
+        let (first,) = module_dests
+            .get("broadcaster")?
+            .iter()
+            .filter(|&dest| upstream.contains(dest))
+            .collect_tuple()?;
+
+        let mut bits: Vec<String> = Vec::new();
+        let mut current = Some(first);
+        while let Some(bit) = current {
+            if !matches!(
+                module_states.get(bit),
+                Some(ModuleState::FlipFlop { on: false })
+            ) || bits.contains(bit)
+            {
+                return None;
+            }
+            bits.push(bit.to_string());
+            let mut next = module_dests[bit].iter().filter(|&dest| dest != counter);
+            current = next.next();
+            if next.next().is_some() {
+                return None;
+            }
+        }
+        if upstream.len() != bits.len() + 2 || watched.values().any(|&high| high) {
+            return None;
+        }
+
+        // pulsing every clear bit and the lowest bit carries past the top bit, back to 0
+        let resets = &module_dests[counter];
+        let mut value: usize = 0;
+        for (k, bit) in bits.iter().enumerate() {
+            let set = module_dests[bit].contains(counter);
+            let reset = resets.contains(bit);
+            if (k == 0 && !(set && reset)) || (k > 0 && set == reset) {
+                return None;
+            }
+            if set {
+                value |= 1 << k;
+            }
+        }
+        let set_bits = value.count_ones() as usize;
+        if watched.len() != set_bits || resets.len() != bits.len() - set_bits + 2 {
+            return None;
+        }
+        Some((bits, value))
+    }
+
+    /// Work out when `target` first gets a low pulse, assuming it is fed by a single
+    /// conjunction whose inputs depend on disjoint parts of the network.
+    pub fn analyze(
+        module_states: &HashMap<String, ModuleState>,
+        module_dests: &HashMap<String, Vec<String>>,
+        target: &str,
+    ) -> Result<Analysis, AssumptionFailed> {
+        let feeders = module_dests
+            .iter()
+            .filter(|&(_, dests)| dests.iter().any(|dest| dest == target))
+            .map(|(module, _)| module.to_string())
+            .sorted()
+            .collect::<Vec<_>>();
+        let feeder = match &feeders[..] {
+            [] => return Err(AssumptionFailed::NoFeeder(target.to_string())),
+            [feeder] => feeder,
+            _ => {
+                return Err(AssumptionFailed::SeveralFeeders(
+                    target.to_string(),
+                    feeders,
+                ))
+            }
+        };
+        let Some(ModuleState::Conjunction { inputs }) = module_states.get(feeder) else {
+            return Err(AssumptionFailed::FeederNotConjunction(feeder.to_string()));
+        };
+
+        let mut subcircuits: Vec<Subcircuit> = Vec::new();
+        for output in inputs.keys().sorted() {
+            let mut modules = Self::upstream(module_dests, output)
+                .into_iter()
+                .collect::<BTreeSet<_>>();
+            modules.remove("broadcaster");
+            for other in subcircuits.iter() {
+                if let Some(shared) = modules.intersection(&other.modules).next() {
+                    return Err(AssumptionFailed::SharedModule(
+                        other.output.to_string(),
+                        output.to_string(),
+                        shared.to_string(),
+                    ));
+                }
+            }
+            let (cycle, presses, decoding) =
+                match Self::decode_counter(module_states, module_dests, output) {
+                    Some((bits, value)) => (
+                        Cycle {
+                            prefix: 0,
+                            period: value,
+                        },
+                        vec![value],
+                        Decoding::Counter { bits, value },
+                    ),
+                    None => {
+                        let (cycle, presses) =
+                            Self::firing_cycle(module_states, module_dests, output);
+                        (cycle, presses, Decoding::Simulated)
                     }
-                    *on = !*on;
-                    *on
+                };
+            if presses.is_empty() {
+                return Err(AssumptionFailed::NeverFires(output.to_string()));
+            }
+            subcircuits.push(Subcircuit {
+                output: output.to_string(),
+                modules,
+                cycle,
+                presses,
+                decoding,
+            });
+        }
+        Ok(Analysis {
+            feeder: feeder.to_string(),
+            subcircuits,
+        })
+    }
+
+    /// The period of each subcircuit feeding the module that sends to `target`,
+    /// and the first press on which they all line up.
+    pub fn cycle_report(input: &str, target: &str) -> String {
+        let (module_states, module_dests) = Self::parse_input(input);
+        let mut out = String::new();
+        match Self::analyze(&module_states, &module_dests, target) {
+            Ok(analysis) => {
+                writeln!(out, "{} feeds {}", analysis.feeder, target).unwrap();
+                for sub in analysis.subcircuits.iter() {
+                    let decoding = match &sub.decoding {
+                        Decoding::Counter { bits, .. } => format!("counter {}", bits.join(" ")),
+                        Decoding::Simulated => "simulated".to_string(),
+                    };
+                    writeln!(
+                        out,
+                        "{}: {}, fires on presses {:?} ({})",
+                        sub.output, sub.cycle, sub.presses, decoding
+                    )
+                    .unwrap();
                 }
-                ModuleState::Conjunction { ref mut inputs } => {
-                    inputs.insert(from, pulse);
-                    !inputs.values().all(|&b| b)
+                match analysis.first_press() {
+                    Ok(n) => writeln!(out, "first press: {}", n).unwrap(),
+                    Err(failed) => writeln!(out, "{}", failed).unwrap(),
                 }
-            };
-            for dest in dests.iter() {
-                queue.push_back((new_pulse, current.to_string(), dest.to_string()));
-                sent.push((new_pulse, current.to_string(), dest.to_string()));
             }
+            Err(failed) => writeln!(out, "{}", failed).unwrap(),
         }
-        sent
+        out
     }
 
-    fn simulate(
+    /// The pulses matching `filter` for each of `presses` button presses.
+    pub fn trace(
         module_states: &mut HashMap<String, ModuleState>,
         module_dests: &HashMap<String, Vec<String>>,
         presses: usize,
-    ) -> Vec<(bool, String, String)> {
-        let mut sent = Vec::new();
-        for _ in 0..presses {
-            sent.append(&mut Self::press_button(module_states, module_dests));
+        filter: &TraceFilter,
+    ) -> Vec<Vec<Pulse>> {
+        (0..presses)
+            .map(|_| {
+                Self::press_button(module_states, module_dests)
+                    .into_iter()
+                    .filter(|pulse| filter.matches(pulse))
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn write_trace(out: &mut impl Write, trace: &[Vec<Pulse>]) -> io::Result<()> {
+        for (i, pulses) in trace.iter().enumerate() {
+            writeln!(out, "press {}", i + 1)?;
+            for pulse in pulses.iter() {
+                writeln!(out, "  {}", format_pulse(pulse))?;
+            }
         }
-        sent
+        Ok(())
     }
 
-    fn simulate_until(
+    /// Step through presses pulse by pulse, reading commands from `input`:
+    /// enter or `n` delivers the next pulse (pressing the button if needed),
+    /// `p` finishes the current press, `s` saves the current state, `r` goes back to it
+    /// and `q` quits.
+    /// The states of all modules are shown after every command.
+    pub fn step_interactively(
         module_states: &mut HashMap<String, ModuleState>,
         module_dests: &HashMap<String, Vec<String>>,
-        module: &String,
-    ) -> usize {
-        let mut presses = 0;
-        loop {
-            presses += 1;
-            let pulses = Self::press_button(module_states, module_dests);
-            if pulses
-                .iter()
-                .position(|(p, from, _to)| from == module && *p == true)
-                .is_some()
-            {
-                return presses;
+        input: impl BufRead,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        let mut stepper = Stepper::new(module_states, module_dests);
+        write!(out, "{}> ", format_states(stepper.module_states))?;
+        out.flush()?;
+        for line in input.lines() {
+            match line?.trim() {
+                "" | "n" => {
+                    if !stepper.is_busy() {
+                        stepper.press();
+                        writeln!(out, "press {}", stepper.presses)?;
+                    }
+                    if let Some(pulse) = stepper.step() {
+                        writeln!(out, "{}", format_pulse(&pulse))?;
+                    }
+                }
+                "p" => {
+                    if !stepper.is_busy() {
+                        stepper.press();
+                        writeln!(out, "press {}", stepper.presses)?;
+                    }
+                    while let Some(pulse) = stepper.step() {
+                        writeln!(out, "{}", format_pulse(&pulse))?;
+                    }
+                }
+                "s" => {
+                    stepper.save();
+                    writeln!(out, "saved during press {}", stepper.presses)?;
+                }
+                "r" => {
+                    if stepper.restore() {
+                        writeln!(out, "restored to press {}", stepper.presses)?;
+                    } else {
+                        writeln!(out, "nothing saved")?;
+                    }
+                }
+                "q" => break,
+                other => writeln!(out, "unknown command {:?}", other)?,
             }
+            write!(out, "{}> ", format_states(stepper.module_states))?;
+            out.flush()?;
         }
+        writeln!(out)
+    }
+
+    /// The first of up to `limit` presses on which `target` gets a low pulse.
+    pub fn brute_force(
+        module_states: &HashMap<String, ModuleState>,
+        module_dests: &HashMap<String, Vec<String>>,
+        target: &str,
+        limit: usize,
+    ) -> Option<usize> {
+        let mut states = module_states.clone();
+        (1..=limit).find(|_| {
+            Self::press_button(&mut states, module_dests)
+                .iter()
+                .any(|(pulse, _, to)| !pulse && to == target)
+        })
     }
 }
 
@@ -133,26 +663,21 @@ impl Solution for Day20 {
     }
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
+        const BRUTE_FORCE_LIMIT: usize = 1_000_000;
         let (module_states, module_dests) = _parsed_input;
-        let conj = module_dests
-            .iter()
-            .filter(|&(_, dests)| dests.contains(&"rx".to_string()))
-            .next()
-            .unwrap()
-            .0;
-        let Some(ModuleState::Conjunction { inputs }) = module_states.get(conj) else {
-            panic!();
-        };
-        inputs
-            .keys()
-            .map(|input| {
-                Self::simulate_until(
-                    &mut module_states.clone(),
-                    &module_dests,
-                    &input.to_string(),
+        Self::analyze(module_states, module_dests, "rx")
+            .and_then(|analysis| analysis.first_press())
+            .or_else(|failed| {
+                Self::brute_force(module_states, module_dests, "rx", BRUTE_FORCE_LIMIT).ok_or_else(
+                    || {
+                        format!(
+                            "Day 20: {}, and rx gets no low pulse in {} presses",
+                            failed, BRUTE_FORCE_LIMIT
+                        )
+                    },
                 )
             })
-            .fold(1, |acc, n| lcm(acc, n))
+            .unwrap_or_else(|e| panic!("{}", e))
             .to_string()
     }
 }
@@ -188,4 +713,232 @@ mod tests {
             "11687500".to_string()
         )
     }
+
+    #[test]
+    fn check_day20_firing_cycle() {
+        let (module_states, module_dests) = Day20::parse_input(
+            "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output",
+        );
+        assert_eq!(
+            Day20::firing_cycle(&module_states, &module_dests, "con"),
+            (
+                Cycle {
+                    prefix: 0,
+                    period: 4
+                },
+                vec![1, 2, 3, 4]
+            )
+        );
+        assert_eq!(
+            Day20::firing_cycle(&module_states, &module_dests, "b"),
+            (
+                Cycle {
+                    prefix: 0,
+                    period: 4
+                },
+                vec![1]
+            )
+        );
+    }
+
+    // two counters, up to 5 and up to 3, each behind an inverter
+    const COUNTERS: &str = "broadcaster -> a0, b0
+%a0 -> a1, ca
+%a1 -> a2
+%a2 -> ca
+&ca -> a0, a1, ia
+&ia -> feed
+%b0 -> b1, cb
+%b1 -> cb
+&cb -> b0, ib
+&ib -> feed
+&feed -> rx";
+
+    #[test]
+    fn check_day20_counters() {
+        let (module_states, module_dests) = Day20::parse_input(COUNTERS);
+        let analysis = Day20::analyze(&module_states, &module_dests, "rx").unwrap();
+        assert_eq!(analysis.feeder, "feed");
+        let decodings = analysis
+            .subcircuits
+            .iter()
+            .map(|sub| sub.decoding.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            decodings,
+            vec![
+                Decoding::Counter {
+                    bits: vec!["a0".to_string(), "a1".to_string(), "a2".to_string()],
+                    value: 5
+                },
+                Decoding::Counter {
+                    bits: vec!["b0".to_string(), "b1".to_string()],
+                    value: 3
+                },
+            ]
+        );
+        // the decoded counters agree with simulating them
+        for sub in analysis.subcircuits.iter() {
+            let (cycle, presses) = Day20::firing_cycle(&module_states, &module_dests, &sub.output);
+            let simulated = Subcircuit {
+                cycle,
+                presses,
+                decoding: Decoding::Simulated,
+                ..sub.clone()
+            };
+            assert!((1..=30).all(|n| sub.fires_on(n) == simulated.fires_on(n)));
+        }
+        assert_eq!(analysis.first_press(), Ok(15));
+        assert_eq!(
+            Day20::brute_force(&module_states, &module_dests, "rx", 100),
+            Some(15)
+        );
+        assert_eq!(Day20::solve_part_two(COUNTERS), "15".to_string());
+        assert_eq!(
+            Day20::cycle_report(COUNTERS, "rx"),
+            "feed feeds rx\n\
+             ia: repeats every 5 after the first 0, fires on presses [5] (counter a0 a1 a2)\n\
+             ib: repeats every 3 after the first 0, fires on presses [3] (counter b0 b1)\n\
+             first press: 15\n"
+        );
+    }
+
+    #[test]
+    fn check_day20_first_press_with_offsets() {
+        let sub = |prefix, period, presses: Vec<usize>| Subcircuit {
+            output: String::new(),
+            modules: BTreeSet::new(),
+            cycle: Cycle { prefix, period },
+            presses,
+            decoding: Decoding::Simulated,
+        };
+        // fires on presses 3, 7, 11, ... and on 2, 5, 8, 11, ...
+        let analysis = Analysis {
+            feeder: String::new(),
+            subcircuits: vec![sub(0, 4, vec![3]), sub(1, 3, vec![2, 5])],
+        };
+        assert_eq!(analysis.first_press(), Ok(11));
+        // a transient press counts too
+        let analysis = Analysis {
+            feeder: String::new(),
+            subcircuits: vec![sub(2, 4, vec![1, 4]), sub(0, 1, vec![1])],
+        };
+        assert_eq!(analysis.first_press(), Ok(1));
+        let analysis = Analysis {
+            feeder: String::new(),
+            subcircuits: vec![sub(0, 2, vec![2]), sub(0, 4, vec![1, 3])],
+        };
+        assert_eq!(analysis.first_press(), Err(AssumptionFailed::NoCommonPress));
+    }
+
+    #[test]
+    fn check_day20_failed_assumptions() {
+        let (module_states, module_dests) = Day20::parse_input("broadcaster -> a\n%a -> rx");
+        assert_eq!(
+            Day20::analyze(&module_states, &module_dests, "rx"),
+            Err(AssumptionFailed::FeederNotConjunction("a".to_string()))
+        );
+        assert_eq!(Day20::solve_part_two("broadcaster -> a\n%a -> rx"), "2");
+        let (module_states, module_dests) = Day20::parse_input(
+            "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output",
+        );
+        assert_eq!(
+            Day20::analyze(&module_states, &module_dests, "output"),
+            Err(AssumptionFailed::SharedModule(
+                "a".to_string(),
+                "b".to_string(),
+                "a".to_string()
+            ))
+        );
+        assert_eq!(
+            Day20::analyze(&module_states, &module_dests, "rx"),
+            Err(AssumptionFailed::NoFeeder("rx".to_string()))
+        );
+    }
+
+    #[test]
+    fn check_day20_trace() {
+        let (mut module_states, module_dests) = Day20::parse_input(
+            "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output",
+        );
+        let filter = TraceFilter {
+            modules: vec!["output".to_string()],
+            level: Some(false),
+        };
+        let trace = Day20::trace(&mut module_states, &module_dests, 4, &filter);
+        assert_eq!(
+            trace.iter().map(|pulses| pulses.len()).collect::<Vec<_>>(),
+            vec![1, 0, 1, 0]
+        );
+        let mut out = Vec::new();
+        Day20::write_trace(&mut out, &trace[..2]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "press 1\n  con -low-> output\npress 2\n"
+        );
+        // after four presses the network is back where it started
+        assert_eq!(
+            module_states,
+            Day20::parse_input(
+                "broadcaster -> a
+%a -> inv, con
+&inv -> b
+%b -> con
+&con -> output",
+            )
+            .0
+        );
+    }
+
+    #[test]
+    fn check_day20_stepper() {
+        let (mut module_states, module_dests) = Day20::parse_input(COUNTERS);
+        let mut expected = module_states.clone();
+        let saved = Snapshot::take(&module_states);
+        let mut stepper = Stepper::new(&mut module_states, &module_dests);
+        stepper.press();
+        let first = stepper.step().unwrap();
+        assert_eq!(format_pulse(&first), "button -low-> broadcaster");
+        assert!(stepper.is_busy());
+        let rest = std::iter::from_fn(|| stepper.step()).collect::<Vec<_>>();
+        let pressed = Day20::press_button(&mut expected, &module_dests);
+        assert_eq!(pressed[0], first);
+        assert_eq!(pressed[1..], rest[..]);
+        assert_eq!(*stepper.module_states, expected);
+        saved.restore(stepper.module_states);
+        assert_eq!(Snapshot::take(stepper.module_states), saved);
+
+        let (mut module_states, _) = Day20::parse_input(COUNTERS);
+        let mut out = Vec::new();
+        let commands = "n\nn\ns\np\nr\np\nq\nn\n";
+        Day20::step_interactively(
+            &mut module_states,
+            &module_dests,
+            commands.as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("press 1\nbutton -low-> broadcaster\n"));
+        assert!(out.contains("broadcaster -low-> a0\n"));
+        assert!(out.contains("%a0 on\n"));
+        assert!(out.contains("&ca a0=high a2=low\n"));
+        assert!(out.contains("restored to press 1\n"));
+        // finishing the press again from the save point ends up after exactly one press
+        let (mut once, _) = Day20::parse_input(COUNTERS);
+        Day20::press_button(&mut once, &module_dests);
+        assert_eq!(module_states, once);
+    }
 }
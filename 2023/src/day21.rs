@@ -1,10 +1,140 @@
+use crate::search;
 use crate::Solution;
-use std::collections::{HashSet, VecDeque};
+use num::integer::lcm;
+use num::rational::Ratio;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
+
+/// Breadth-first search over the garden repeated infinitely in every direction,
+/// expanded one step at a time as larger step counts are asked for.
+struct InfiniteGarden<'a> {
+    grid: &'a [Vec<char>],
+    seen: HashSet<(isize, isize)>,
+    frontier: Vec<(isize, isize)>,
+    /// `totals[d]` is the number of plots reachable in exactly `d` steps
+    totals: Vec<usize>,
+}
+
+impl<'a> InfiniteGarden<'a> {
+    fn new(grid: &'a [Vec<char>], (r, c): (usize, usize)) -> Self {
+        let start = (r as isize, c as isize);
+        InfiniteGarden {
+            grid,
+            seen: HashSet::from([start]),
+            frontier: vec![start],
+            totals: vec![1],
+        }
+    }
+
+    fn is_plot(&self, (r, c): (isize, isize)) -> bool {
+        let (rows, cols) = (self.grid.len() as isize, self.grid[0].len() as isize);
+        self.grid[r.rem_euclid(rows) as usize][c.rem_euclid(cols) as usize] != '#'
+    }
+
+    fn expand_to(&mut self, steps: usize) {
+        while self.totals.len() <= steps && !self.frontier.is_empty() {
+            let mut next = Vec::new();
+            for &(r, c) in self.frontier.iter() {
+                for p in [(r + 1, c), (r, c - 1), (r - 1, c), (r, c + 1)] {
+                    if self.is_plot(p) && self.seen.insert(p) {
+                        next.push(p);
+                    }
+                }
+            }
+            // a plot first reached after d steps can still be reached after d + 2, d + 4, ...
+            let d = self.totals.len();
+            let same_parity = if d >= 2 { self.totals[d - 2] } else { 0 };
+            self.totals.push(same_parity + next.len());
+            self.frontier = next;
+        }
+    }
+
+    /// Whether the search ever leaves the first copy of the garden.
+    fn escapes(&mut self) -> bool {
+        let (rows, cols) = (self.grid.len() as isize, self.grid[0].len() as isize);
+        loop {
+            let outside = self
+                .frontier
+                .iter()
+                .any(|&(r, c)| r < 0 || r >= rows || c < 0 || c >= cols);
+            if outside || self.frontier.is_empty() {
+                return outside;
+            }
+            self.expand_to(self.totals.len());
+        }
+    }
+
+    fn reachable(&mut self, steps: usize) -> usize {
+        self.expand_to(steps);
+        let last = self.totals.len() - 1;
+        if steps <= last {
+            self.totals[steps]
+        } else if (steps - last).is_multiple_of(2) {
+            // the whole reachable area is enclosed and already counted
+            self.totals[last]
+        } else if last >= 1 {
+            self.totals[last - 1]
+        } else {
+            0
+        }
+    }
+}
+
+/// The quadratic `f(k) = a k^2 + b k + c` that the infinite-garden count after
+/// `offset + k * period` steps follows from `k = from` onwards.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Growth {
+    pub period: usize,
+    pub offset: usize,
+    pub from: usize,
+    /// The counts measured by searching, for `k = 0, 1, ...`
+    pub samples: Vec<usize>,
+    pub coefficients: (Ratio<i128>, Ratio<i128>, Ratio<i128>),
+}
+
+impl Growth {
+    /// The count after `offset + k * period` steps.
+    pub fn at(&self, k: usize) -> usize {
+        if let Some(&sample) = self.samples.get(k) {
+            return sample;
+        }
+        let (a, b, c) = self.coefficients;
+        let k = Ratio::from_integer(k as i128);
+        let value = a * k * k + b * k + c;
+        assert!(value.is_integer(), "Day 21: fitted count is not whole");
+        value.to_integer() as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SeriesRow {
+    pub steps: usize,
+    pub finite: usize,
+    pub infinite: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ParitySplit {
+    pub even_within: usize,
+    pub odd_within: usize,
+    pub even_beyond: usize,
+    pub odd_beyond: usize,
+    /// Plots walled off from the start.
+    pub unreachable: usize,
+}
+
+impl ParitySplit {
+    /// Number of reachable plots.
+    pub fn total(&self) -> usize {
+        self.even_within + self.odd_within + self.even_beyond + self.odd_beyond
+    }
+}
 
 pub struct Day21;
 
 impl Day21 {
-    fn start(grid: &Vec<Vec<char>>) -> (usize, usize) {
+    fn start(grid: &[Vec<char>]) -> (usize, usize) {
         for r in 0..grid.len() {
             for c in 0..grid[r].len() {
                 if grid[r][c] == 'S' {
@@ -15,43 +145,176 @@ impl Day21 {
         panic!("Day 21: start not found");
     }
 
-    fn reachable(grid: &Vec<Vec<char>>, steps: Option<usize>) -> HashSet<(usize, (usize, usize))> {
-        let start = Self::start(grid);
+    fn neighbours(grid: &[Vec<char>], (r, c): (usize, usize)) -> Vec<(usize, usize)> {
         let (rows, cols) = (grid.len() as isize, grid[0].len() as isize);
-        let mut queue = VecDeque::from([(0, start)]);
-        let mut reached = HashSet::new();
-        let mut visited = HashSet::new();
-        while let Some((mut i, (r, c))) = queue.pop_front() {
-            if visited.contains(&(r, c)) {
+        let (r, c) = (r as isize, c as isize);
+        let mut next = Vec::new();
+        for (nr, nc) in [(r + 1, c), (r, c - 1), (r - 1, c), (r, c + 1)] {
+            if nr < 0 || nr >= rows || nc < 0 || nc >= cols {
                 continue;
             }
-            visited.insert((r, c));
-            reached.insert((i, (r, c)));
-            if let Some(limit) = steps {
-                if i == limit {
-                    continue;
-                }
+            let (unr, unc) = (nr as usize, nc as usize);
+            if grid[unr][unc] == '#' {
+                continue;
             }
-            i += 1;
-            let (r, c) = (r as isize, c as isize);
-            for (nr, nc) in [(r + 1, c), (r, c - 1), (r - 1, c), (r, c + 1)] {
-                if nr < 0 || nr >= rows || nc < 0 || nc >= cols {
-                    continue;
-                }
-                let (unr, unc) = (nr as usize, nc as usize);
-                if grid[unr][unc] == '#' {
-                    continue;
-                }
-                queue.push_back((i, (unr, unc)));
+            next.push((unr, unc));
+        }
+        next
+    }
+
+    fn reachable(grid: &[Vec<char>], steps: Option<usize>) -> HashSet<(usize, (usize, usize))> {
+        let start = Self::start(grid);
+        search::bfs([start], |&p| Self::neighbours(grid, p), |_| false)
+            .dist
+            .into_iter()
+            .filter(|&(_, i)| steps.is_none_or(|limit| i <= limit))
+            .map(|(p, i)| (i, p))
+            .collect()
+    }
+
+    /// The step period over which the infinite-garden count grows regularly.
+    fn period(grid: &[Vec<char>]) -> usize {
+        if InfiniteGarden::new(grid, Self::start(grid)).escapes() {
+            lcm(grid.len(), grid[0].len())
+        } else {
+            // the count only depends on the parity of the step count
+            2
+        }
+    }
+
+    /// Measure the infinite-garden counts after `offset`, `offset + p`, `offset + 2p`, ...
+    /// steps and fit the quadratic they eventually follow, where `p` is `Self::period`.
+    ///
+    /// Once the search has spread over a few copies of the garden, every further period
+    /// adds a ring of whole copies that grows linearly, so the second differences of the
+    /// samples are eventually constant. We keep sampling until several of them agree.
+    pub fn fit_growth(grid: &[Vec<char>], offset: usize) -> Growth {
+        const AGREEING_DIFFERENCES: usize = 4;
+        const MAX_PERIODS: usize = 1000;
+        let mut garden = InfiniteGarden::new(grid, Self::start(grid));
+        let period = Self::period(grid);
+        let mut samples = Vec::new();
+        for k in 0..MAX_PERIODS {
+            samples.push(garden.reachable(offset + k * period));
+            let differences = samples
+                .windows(3)
+                .rev()
+                .take(AGREEING_DIFFERENCES)
+                .map(|w| w[2] as i128 - 2 * w[1] as i128 + w[0] as i128)
+                .collect::<Vec<_>>();
+            if differences.len() < AGREEING_DIFFERENCES
+                || differences.iter().any(|&d| d != differences[0])
+            {
+                continue;
+            }
+            // f(k) = a k^2 + b k + c through the last three samples
+            let f = |k: usize| Ratio::from_integer(samples[k] as i128);
+            let kr = Ratio::from_integer(k as i128);
+            let a = Ratio::new(differences[0], 2);
+            let b = f(k) - f(k - 1) - a * (kr * 2 - 1);
+            let c = f(k) - a * kr * kr - b * kr;
+            return Growth {
+                period,
+                offset,
+                from: k + 1 - AGREEING_DIFFERENCES - 2,
+                samples,
+                coefficients: (a, b, c),
+            };
+        }
+        panic!("Day 21: no quadratic growth after {} periods", MAX_PERIODS);
+    }
+
+    /// Number of plots reachable in exactly `steps` steps when the garden repeats forever.
+    pub fn infinite_reachable(grid: &[Vec<char>], steps: usize) -> usize {
+        let period = Self::period(grid);
+        Self::fit_growth(grid, steps % period).at(steps / period)
+    }
+
+    /// Reachable plot counts for every step count in `steps`,
+    /// on the garden by itself and on the infinitely tiled garden.
+    pub fn series(grid: &[Vec<char>], steps: RangeInclusive<usize>) -> Vec<SeriesRow> {
+        let distances = Self::reachable(grid, Some(*steps.end()));
+        let mut garden = InfiniteGarden::new(grid, Self::start(grid));
+        steps
+            .map(|k| SeriesRow {
+                steps: k,
+                finite: distances
+                    .iter()
+                    .filter(|&&(i, _)| i <= k && i % 2 == k % 2)
+                    .count(),
+                infinite: garden.reachable(k),
+            })
+            .collect()
+    }
+
+    /// How the plots of the garden by itself split by the parity of their distance from
+    /// the start, and whether they are within `radius` steps of it.
+    pub fn parity_split(grid: &[Vec<char>], radius: usize) -> ParitySplit {
+        let mut split = ParitySplit::default();
+        for (i, _) in Self::reachable(grid, None) {
+            match (i % 2 == 0, i <= radius) {
+                (true, true) => split.even_within += 1,
+                (false, true) => split.odd_within += 1,
+                (true, false) => split.even_beyond += 1,
+                (false, false) => split.odd_beyond += 1,
             }
         }
-        reached
+        let plots = grid.iter().flatten().filter(|&&c| c != '#').count();
+        split.unreachable = plots - split.total();
+        split
+    }
+
+    /// A table of the step-count series for `steps`, followed by the parity split of the
+    /// garden around its half-width and the quadratic fitted for the last step count.
+    pub fn report(input: &str, steps: RangeInclusive<usize>) -> String {
+        let grid = Self::parse_input(input);
+        let mut out = String::new();
+        writeln!(out, "steps\tfinite\tinfinite").unwrap();
+        for row in Self::series(&grid, steps.clone()) {
+            writeln!(out, "{}\t{}\t{}", row.steps, row.finite, row.infinite).unwrap();
+        }
+        let radius = grid.len().max(grid[0].len()) / 2;
+        let split = Self::parity_split(&grid, radius);
+        writeln!(
+            out,
+            "parity within {} steps: even {}, odd {}",
+            radius, split.even_within, split.odd_within
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "parity beyond {} steps: even {}, odd {}",
+            radius, split.even_beyond, split.odd_beyond
+        )
+        .unwrap();
+        writeln!(out, "unreachable plots: {}", split.unreachable).unwrap();
+        let growth = Self::fit_growth(&grid, steps.end() % Self::period(&grid));
+        let (a, b, c) = growth.coefficients;
+        let signed = |x: Ratio<i128>| {
+            if x < Ratio::from_integer(0) {
+                format!("- {}", -x)
+            } else {
+                format!("+ {}", x)
+            }
+        };
+        writeln!(
+            out,
+            "after {} + {}k steps: f(k) = {} k^2 {} k {} for k >= {}",
+            growth.offset,
+            growth.period,
+            a,
+            signed(b),
+            signed(c),
+            growth.from
+        )
+        .unwrap();
+        out
     }
 
-    fn reachable_in_steps(grid: &Vec<Vec<char>>, steps: usize) -> HashSet<(usize, usize)> {
+    fn reachable_in_steps(grid: &[Vec<char>], steps: usize) -> HashSet<(usize, usize)> {
         Self::reachable(grid, Some(steps))
             .iter()
-            .filter(|&(i, _)| i % 2 == 0)
+            .filter(|&(i, _)| i % 2 == steps % 2)
             .map(|&(_, (r, c))| (r, c))
             .collect()
     }
@@ -74,62 +337,7 @@ impl Solution for Day21 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
-
-        /* the input contains two diamonds.
-         * let's call them the corner diamond and the center diamond.
-         * the center and bordering rows and columns are clear.
-         * the S is in the center of the center diamond.
-         *
-         * the size of the input is 131 x 131.
-         * it takes (131 - 1) / 2 = 65 steps to reach the side from S.
-         *
-         * given no obstructions, we would expect the reachable steps
-         * to form a kind of banded-diamond expanding outward.
-         * the center columns/rows are clear,
-         * so we know the centers will be reachable.
-         * 131 / 2 = 65 is odd, so from S, the edges will not be reachable
-         * so reachable tiles will alternate in parity as we go outward.
-         *
-         * steps = 65 + 131 * x
-         *
-         * A = center diamond (same parity as center) (odd in our case)
-         * A' = center diamond (alt. parity)
-         * B = corners (same parity as center)
-         * B' = corners (alt. parity)
-         * C = corner diamond = (B + B') / 2
-         *
-         * ((n + 1)^2 - (n - 1)^2) / 2 = 4 * (n - 1)
-         * https://en.wikipedia.org/wiki/Difference_of_two_squares#Difference_of_two_consecutive_perfect_squares
-         *
-         * f(0) = A
-         * f(1) = f(0) + 4*1 (A' + C) = A + 4A' + 4C
-         * f(2) = f(1) + 4*2 (A + C)  = 9A + 4A' + 12C
-         * f(3) = f(2) + 4*3 (A' + C) = 9A + 16A' + 24C
-         * f(4) = f(3) + 4*4 (A + C)  = 25A + 16A' + 40C
-         * f(5) = f(4) + 4*5 (A' + C) = 25A + 36A' + 60C
-         * f(6) = f(5) + 4*6 (A + C)  = 49A + 36A' + 84C
-         *  odd x: f(x) = x^2 A + (x+1)^2 A' + 2x(x+1) C
-         * even x: f(x) = (x+1)^2 A + x^2 A' + 2x(x+1) C
-         */
-
-        let reached = Self::reachable(&grid, None);
-
-        let odd_center_diamond = reached
-            .iter()
-            .filter(|&(i, _)| *i % 2 != 0 && *i <= 65)
-            .count();
-        let even_center_diamond = reached
-            .iter()
-            .filter(|&(i, _)| *i % 2 == 0 && *i <= 65)
-            .count();
-        let corner_diamond = reached.iter().filter(|&(i, _)| *i > 65).count() / 2;
-
-        let x = 202300; // (26501365 - 65) / 131
-        let result = (x + 1) * (x + 1) * odd_center_diamond
-            + x * x * even_center_diamond
-            + 2 * x * (x + 1) * corner_diamond;
-
-        result.to_string()
+        Self::infinite_reachable(grid, 26501365).to_string()
     }
 }
 
@@ -156,4 +364,89 @@ mod tests {
             16
         )
     }
+
+    #[test]
+    fn check_day21_odd_steps() {
+        // walk every step explicitly, so the plots reached are exactly those at the right parity
+        let grid = Day21::parse_input(TEST_INPUT);
+        let mut plots = HashSet::from([Day21::start(&grid)]);
+        for steps in 1..=15 {
+            plots = plots
+                .iter()
+                .flat_map(|&(r, c)| {
+                    [
+                        (r + 1, c),
+                        (r, c + 1),
+                        (r.wrapping_sub(1), c),
+                        (r, c.wrapping_sub(1)),
+                    ]
+                })
+                .filter(|&(r, c)| r < grid.len() && c < grid[0].len() && grid[r][c] != '#')
+                .collect();
+            assert_eq!(Day21::reachable_in_steps(&grid, steps), plots);
+        }
+        assert_eq!(Day21::reachable_in_steps(&grid, 1).len(), 2);
+        assert_eq!(Day21::reachable_in_steps(&grid, 3).len(), 6);
+    }
+
+    #[test]
+    fn check_day21_series() {
+        let grid = Day21::parse_input(TEST_INPUT);
+        let series = Day21::series(&grid, 0..=30);
+        assert_eq!(series[6].finite, 16);
+        assert_eq!(series[6].infinite, 16);
+        assert_eq!(series[10].infinite, 50);
+        assert_eq!(series[7].finite, Day21::reachable_in_steps(&grid, 7).len());
+        // the single garden fills up and then alternates between its two parities
+        let split = Day21::parity_split(&grid, 5);
+        assert_eq!(series[30].finite, split.even_within + split.even_beyond);
+        assert_eq!(series[29].finite, split.odd_within + split.odd_beyond);
+        assert!(series[30].infinite > series[30].finite);
+        assert_eq!(split.unreachable, 0);
+        let pocket = Day21::parse_input("#######\n#.S.#.#\n#######");
+        let split = Day21::parity_split(&pocket, 1);
+        assert_eq!((split.even_within, split.odd_within), (1, 2));
+        assert_eq!(split.unreachable, 1);
+    }
+
+    #[test]
+    fn check_day21_part2_case1() {
+        let grid = Day21::parse_input(TEST_INPUT);
+        for (steps, expected) in [
+            (6, 16),
+            (10, 50),
+            (50, 1594),
+            (100, 6536),
+            (500, 167004),
+            (1000, 668697),
+            (5000, 16733044),
+        ] {
+            assert_eq!(Day21::infinite_reachable(&grid, steps), expected);
+        }
+    }
+
+    #[test]
+    fn check_day21_extrapolation_matches_search() {
+        let grid = Day21::parse_input(TEST_INPUT);
+        let mut garden = InfiniteGarden::new(&grid, Day21::start(&grid));
+        for steps in [64, 300, 301, 777] {
+            assert_eq!(
+                Day21::infinite_reachable(&grid, steps),
+                garden.reachable(steps)
+            );
+        }
+        let growth = Day21::fit_growth(&grid, 6);
+        assert_eq!(growth.period, 11);
+        let (a, b, c) = growth.coefficients;
+        for k in growth.from..growth.samples.len() + 3 {
+            let steps = 6 + 11 * k;
+            let expected = a * (k as i128).pow(2) + b * k as i128 + c;
+            assert_eq!(growth.at(k) as i128, expected.to_integer());
+            assert_eq!(growth.at(k), garden.reachable(steps));
+        }
+        // a garden that is walled in never grows
+        let walled = Day21::parse_input("#####\n#.S.#\n#####");
+        assert_eq!(Day21::infinite_reachable(&walled, 1001), 2);
+        assert_eq!(Day21::infinite_reachable(&walled, 1000), 1);
+    }
 }
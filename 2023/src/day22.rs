@@ -3,6 +3,8 @@ use itertools::iproduct;
 use itertools::Itertools;
 use std::cmp;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead, Write};
 use std::str::FromStr;
 
 pub struct Day22;
@@ -31,6 +33,46 @@ impl FromStr for Coordinate {
     }
 }
 
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+impl fmt::Display for Brick {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}~{}", self.0, self.1)
+    }
+}
+
+impl Brick {
+    fn min(&self) -> Coordinate {
+        Coordinate {
+            x: cmp::min(self.0.x, self.1.x),
+            y: cmp::min(self.0.y, self.1.y),
+            z: cmp::min(self.0.z, self.1.z),
+        }
+    }
+
+    fn max(&self) -> Coordinate {
+        Coordinate {
+            x: cmp::max(self.0.x, self.1.x),
+            y: cmp::max(self.0.y, self.1.y),
+            z: cmp::max(self.0.z, self.1.z),
+        }
+    }
+}
+
+/// The highest point of the settled stack and the bricks piled up under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub x: isize,
+    pub y: isize,
+    pub height: isize,
+    /// From the bottom up.
+    pub bricks: Vec<usize>,
+}
+
 impl FromStr for Brick {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -41,6 +83,318 @@ impl FromStr for Brick {
     }
 }
 
+/// The settled stack as a support graph, with bricks numbered from the bottom up
+/// so that every brick comes after the bricks holding it up.
+pub struct Stack {
+    bricks: Vec<Brick>,
+    /// The settled brick each brick of the input ended up as.
+    settled_from: HashMap<Brick, usize>,
+    below: Vec<Vec<usize>>,
+    above: Vec<Vec<usize>>,
+    /// For each brick, how many bricks have to go through it to reach the ground
+    /// (itself included), i.e. the size of its subtree in the dominator tree.
+    dominated: Vec<usize>,
+}
+
+impl Stack {
+    pub fn new(bricks: &Vec<Brick>) -> Self {
+        let supported_by = Day22::supported_by(bricks);
+        let settled = supported_by
+            .keys()
+            .copied()
+            .sorted_by_key(|b| (cmp::min(b.0.z, b.1.z), b.0.x, b.0.y, b.1.x, b.1.y))
+            .collect::<Vec<_>>();
+        let index = settled
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| (b, i))
+            .collect::<HashMap<_, _>>();
+        let below = settled
+            .iter()
+            .map(|b| supported_by[b].iter().map(|s| index[s]).sorted().collect())
+            .collect::<Vec<Vec<usize>>>();
+        let mut above = vec![Vec::new(); settled.len()];
+        for (i, supports) in below.iter().enumerate() {
+            for &s in supports {
+                above[s].push(i);
+            }
+        }
+
+        // Bricks only move down and never pass each other, so bricks of the same shape
+        // settle in the same order they started in.
+        let shape = |b: &Brick| {
+            let (min, max) = (b.min(), b.max());
+            (min.x, min.y, max.x, max.y, max.z - min.z)
+        };
+        let mut by_shape = HashMap::<_, Vec<usize>>::new();
+        for (i, b) in settled.iter().enumerate() {
+            by_shape.entry(shape(b)).or_default().push(i);
+        }
+        let mut settled_from = HashMap::new();
+        for b in bricks.iter().sorted_by_key(|b| b.min().z) {
+            let same_shape = by_shape.get_mut(&shape(b)).unwrap();
+            settled_from.insert(*b, same_shape.remove(0));
+        }
+
+        // The support graph is acyclic and numbered in topological order, so a brick's
+        // immediate dominator is the lowest common ancestor of its supporters in the tree
+        // built so far. The ground is node `n`, and ancestors are found by binary lifting.
+        let n = settled.len();
+        let levels = (usize::BITS - n.leading_zeros()) as usize + 1;
+        let mut up = vec![vec![n; n + 1]; levels];
+        let mut depth = vec![0; n + 1];
+        let lca = |up: &Vec<Vec<usize>>, depth: &Vec<usize>, mut a: usize, mut b: usize| {
+            if depth[a] < depth[b] {
+                (a, b) = (b, a);
+            }
+            for k in (0..levels).rev() {
+                if depth[a] - depth[b] >= 1 << k {
+                    a = up[k][a];
+                }
+            }
+            if a == b {
+                return a;
+            }
+            for k in (0..levels).rev() {
+                if up[k][a] != up[k][b] {
+                    (a, b) = (up[k][a], up[k][b]);
+                }
+            }
+            up[0][a]
+        };
+        let mut idom = vec![n; n];
+        for v in 0..n {
+            idom[v] = below[v]
+                .iter()
+                .copied()
+                .reduce(|a, b| lca(&up, &depth, a, b))
+                .unwrap_or(n);
+            depth[v] = depth[idom[v]] + 1;
+            up[0][v] = idom[v];
+            for k in 1..levels {
+                up[k][v] = up[k - 1][up[k - 1][v]];
+            }
+        }
+        let mut dominated = vec![1; n];
+        for v in (0..n).rev() {
+            if idom[v] < n {
+                dominated[idom[v]] += dominated[v];
+            }
+        }
+
+        Stack {
+            bricks: settled,
+            settled_from,
+            below,
+            above,
+            dominated,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bricks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bricks.is_empty()
+    }
+
+    /// Where brick `i` came to rest.
+    pub fn brick(&self, i: usize) -> Brick {
+        self.bricks[i]
+    }
+
+    /// The number of the brick that `brick` from the input settled as.
+    pub fn index_of(&self, brick: &Brick) -> Option<usize> {
+        self.settled_from.get(brick).copied()
+    }
+
+    /// The bricks directly under brick `i` that hold it up.
+    pub fn supporters(&self, i: usize) -> &[usize] {
+        &self.below[i]
+    }
+
+    /// The bricks resting directly on brick `i`.
+    pub fn dependents(&self, i: usize) -> &[usize] {
+        &self.above[i]
+    }
+
+    /// Whether brick `i` can be disintegrated without anything else falling.
+    pub fn is_safe(&self, i: usize) -> bool {
+        self.above[i].iter().all(|&j| self.below[j].len() > 1)
+    }
+
+    /// The column the stack reaches highest in, taking the lowest `(x, y)` on ties.
+    pub fn tallest_column(&self) -> Option<Column> {
+        let (x, y, height) = self
+            .bricks
+            .iter()
+            .flat_map(|b| {
+                let (min, max) = (b.min(), b.max());
+                iproduct!(min.x..=max.x, min.y..=max.y).map(move |(x, y)| (x, y, max.z))
+            })
+            .min_by_key(|&(x, y, z)| (cmp::Reverse(z), x, y))?;
+        let bricks = (0..self.len())
+            .filter(|&i| {
+                let (min, max) = (self.bricks[i].min(), self.bricks[i].max());
+                (min.x..=max.x).contains(&x) && (min.y..=max.y).contains(&y)
+            })
+            .collect();
+        Some(Column {
+            x,
+            y,
+            height,
+            bricks,
+        })
+    }
+
+    /// How many other bricks fall if brick `i` is disintegrated.
+    pub fn falls_without(&self, i: usize) -> usize {
+        self.dominated[i] - 1
+    }
+
+    /// The bricks that fall if all of `removed` are disintegrated at once,
+    /// not counting the removed bricks themselves.
+    pub fn cascade(&self, removed: &[usize]) -> Vec<usize> {
+        let mut gone = vec![false; self.len()];
+        let Some(&lowest) = removed.iter().min() else {
+            return Vec::new();
+        };
+        for &i in removed {
+            gone[i] = true;
+        }
+        let mut fallen = Vec::new();
+        for i in lowest..self.len() {
+            if !gone[i] && !self.below[i].is_empty() && self.below[i].iter().all(|&s| gone[s]) {
+                gone[i] = true;
+                fallen.push(i);
+            }
+        }
+        fallen
+    }
+
+    /// Write the settled stack as a Wavefront OBJ mesh with one box per brick,
+    /// coloured (as vertex colours) from blue for bricks nothing depends on
+    /// to red for the brick the most others depend on.
+    pub fn write_obj(&self, out: &mut impl Write) -> io::Result<()> {
+        let most = (0..self.len())
+            .map(|i| self.falls_without(i))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        // corners are numbered by bits: 1 for the far x, 2 for the far y, 4 for the top
+        const FACES: [[usize; 4]; 6] = [
+            [0, 2, 3, 1],
+            [4, 5, 7, 6],
+            [0, 1, 5, 4],
+            [2, 6, 7, 3],
+            [0, 4, 6, 2],
+            [1, 3, 7, 5],
+        ];
+        for (i, brick) in self.bricks.iter().enumerate() {
+            let (min, max) = (brick.min(), brick.max());
+            let heat = self.falls_without(i) as f64 / most as f64;
+            writeln!(
+                out,
+                "# {}, {} fall without it",
+                brick,
+                self.falls_without(i)
+            )?;
+            writeln!(out, "o brick{}", i)?;
+            for corner in 0..8 {
+                let pick = |bit: usize, lo: isize, hi: isize| {
+                    if corner & bit == 0 {
+                        lo
+                    } else {
+                        hi + 1
+                    }
+                };
+                writeln!(
+                    out,
+                    "v {} {} {} {:.3} {:.3} {:.3}",
+                    pick(1, min.x, max.x),
+                    pick(2, min.y, max.y),
+                    pick(4, min.z, max.z),
+                    heat,
+                    0.2,
+                    1.0 - heat
+                )?;
+            }
+            for face in FACES {
+                let [a, b, c, d] = face.map(|corner| 8 * i + corner + 1);
+                writeln!(out, "f {} {} {} {}", a, b, c, d)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer queries about the stack read line by line from `input`:
+    /// `brick N`, `safe [N]`, `cascade N...`, `tallest` and `q`.
+    pub fn inspect(&self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        write!(out, "{} bricks> ", self.len())?;
+        out.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            let mut words = line.split_whitespace();
+            let command = words.next().unwrap_or("");
+            let numbers = words
+                .map(|w| w.parse::<usize>().ok().filter(|&i| i < self.len()))
+                .collect::<Option<Vec<_>>>();
+            match (command, numbers) {
+                ("q", _) => break,
+                (_, None) if self.is_empty() => writeln!(out, "there are no bricks")?,
+                (_, None) => writeln!(out, "bricks are numbered 0 to {}", self.len() - 1)?,
+                ("brick", Some(ids)) if ids.len() == 1 => {
+                    let i = ids[0];
+                    writeln!(
+                        out,
+                        "{}: {} on {:?} under {:?}, {} fall without it",
+                        i,
+                        self.brick(i),
+                        self.supporters(i),
+                        self.dependents(i),
+                        self.falls_without(i)
+                    )?;
+                }
+                ("brick", _) => writeln!(out, "usage: brick N")?,
+                ("safe", Some(ids)) if ids.is_empty() => {
+                    let safe = (0..self.len())
+                        .filter(|&i| self.is_safe(i))
+                        .collect::<Vec<_>>();
+                    writeln!(out, "{} safe: {:?}", safe.len(), safe)?;
+                }
+                ("safe", Some(ids)) => {
+                    for i in ids {
+                        writeln!(
+                            out,
+                            "{}: {}",
+                            i,
+                            if self.is_safe(i) { "safe" } else { "not safe" }
+                        )?;
+                    }
+                }
+                ("cascade", Some(ids)) if !ids.is_empty() => {
+                    let fallen = self.cascade(&ids);
+                    writeln!(out, "{} fall: {:?}", fallen.len(), fallen)?;
+                }
+                ("cascade", _) => writeln!(out, "usage: cascade N...")?,
+                ("tallest", _) => match self.tallest_column() {
+                    Some(column) => writeln!(
+                        out,
+                        "{},{} reaches {} with {:?}",
+                        column.x, column.y, column.height, column.bricks
+                    )?,
+                    None => writeln!(out, "no bricks")?,
+                },
+                (other, _) => writeln!(out, "unknown command {:?}", other)?,
+            }
+            write!(out, "{} bricks> ", self.len())?;
+            out.flush()?;
+        }
+        writeln!(out)
+    }
+}
+
 impl Day22 {
     fn supported_by(bricks: &Vec<Brick>) -> HashMap<Brick, HashSet<Brick>> {
         // map from (x, y) to (highest z, brick at that coordinate)
@@ -121,31 +475,10 @@ impl Solution for Day22 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let bricks = _parsed_input;
-        let supported_by = Self::supported_by(&bricks);
-        let mut graph = HashMap::new();
-        for (&k, vs) in supported_by.iter() {
-            for v in vs.iter() {
-                let entry = graph.entry(v).or_insert(HashSet::new());
-                entry.insert(k);
-            }
-        }
-        let mut result = 0;
-        for (&support, directs) in graph.iter() {
-            let mut fallen = HashSet::from([support]);
-            let mut stack = Vec::from_iter(directs);
-            while let Some(brick) = stack.pop() {
-                let Some(supports) = supported_by.get(brick) else {
-                    continue;
-                };
-                if supports.iter().all(|b| fallen.contains(b)) {
-                    fallen.insert(brick);
-                    if let Some(directs) = graph.get(brick) {
-                        stack.extend(directs);
-                    }
-                }
-            }
-            result += fallen.len() - 1;
-        }
+        let stack = Stack::new(bricks);
+        let result = (0..stack.len())
+            .map(|i| stack.falls_without(i))
+            .sum::<usize>();
         result.to_string()
     }
 }
@@ -171,4 +504,128 @@ mod tests {
     fn check_day22_part2_case1() {
         assert_eq!(Day22::solve_part_two(TEST_INPUT), "7".to_string())
     }
+
+    /// Count the falling bricks the slow way, flooding up from each removed brick.
+    fn flood(bricks: &Vec<Brick>, removed: &[Brick]) -> usize {
+        let supported_by = Day22::supported_by(bricks);
+        let mut fallen = removed.iter().copied().collect::<HashSet<_>>();
+        for brick in supported_by
+            .keys()
+            .sorted_by_key(|b| cmp::min(b.0.z, b.1.z))
+        {
+            let supports = &supported_by[brick];
+            if !supports.is_empty() && supports.iter().all(|b| fallen.contains(b)) {
+                fallen.insert(*brick);
+            }
+        }
+        fallen.len() - removed.len()
+    }
+
+    #[test]
+    fn check_day22_dominators() {
+        // a pile of random bricks from a fixed seed, plus the example
+        let mut seed = 12345u64;
+        let mut random = |n: isize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as isize % n
+        };
+        let mut pile = Vec::new();
+        for z in 1..200 {
+            let (x, y, length) = (random(6), random(6), random(3));
+            let end = match random(3) {
+                0 => Coordinate {
+                    x: (x + length).min(5),
+                    y,
+                    z,
+                },
+                1 => Coordinate {
+                    x,
+                    y: (y + length).min(5),
+                    z,
+                },
+                _ => Coordinate {
+                    x,
+                    y,
+                    z: z + length,
+                },
+            };
+            pile.push(format!("{},{},{}~{},{},{}", x, y, z, end.x, end.y, end.z));
+        }
+        for input in [TEST_INPUT.to_string(), pile.join("\n")] {
+            let bricks = Day22::parse_input(&input);
+            let stack = Stack::new(&bricks);
+            assert!((0..stack.len()).any(|i| stack.falls_without(i) > 1));
+            for i in 0..stack.len() {
+                let expected = flood(&bricks, &[stack.bricks[i]]);
+                assert_eq!(stack.falls_without(i), expected);
+                assert_eq!(stack.cascade(&[i]).len(), expected);
+            }
+            for pair in (0..stack.len()).step_by(7).combinations(2) {
+                let removed = pair.iter().map(|&i| stack.bricks[i]).collect::<Vec<_>>();
+                assert_eq!(stack.cascade(&pair).len(), flood(&bricks, &removed));
+            }
+        }
+    }
+
+    #[test]
+    fn check_day22_queries() {
+        let bricks = Day22::parse_input(TEST_INPUT);
+        let stack = Stack::new(&bricks);
+        let a = stack.index_of(&bricks[0]).unwrap();
+        let g = stack.index_of(&bricks[6]).unwrap();
+        assert_eq!(stack.brick(g).to_string(), "1,1,5~1,1,6");
+        assert_eq!(stack.supporters(a), &[] as &[usize]);
+        assert_eq!(stack.dependents(a).len(), 2);
+        let safe = (0..stack.len()).filter(|&i| stack.is_safe(i)).count();
+        assert_eq!(safe, 5);
+        assert!(!stack.is_safe(a) && stack.is_safe(g));
+        assert_eq!(stack.cascade(&[a]).len(), 6);
+        let column = stack.tallest_column().unwrap();
+        assert_eq!((column.x, column.y, column.height), (1, 1, 6));
+        assert_eq!(column.bricks.last(), Some(&g));
+        assert_eq!(column.bricks.len(), 3);
+
+        let mut out = Vec::new();
+        let commands = format!("brick {}\nsafe\ncascade {}\ntallest\nbrick 99\nq\n", g, a);
+        stack.inspect(commands.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("1,1,5~1,1,6 on"));
+        assert!(out.contains("5 safe:"));
+        assert!(out.contains("6 fall:"));
+        assert!(out.contains("1,1 reaches 6"));
+        assert!(out.contains("numbered 0 to 6"));
+
+        let mut out = Vec::new();
+        stack
+            .inspect("brick\ncascade\nbrick 1 2\n".as_bytes(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("usage: brick N\n").count(), 2);
+        assert!(out.contains("usage: cascade N...\n"));
+        assert!(!out.contains("unknown command"));
+
+        let empty = Stack::new(&Vec::new());
+        let mut out = Vec::new();
+        empty
+            .inspect("brick 0\nsafe\nq\n".as_bytes(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("there are no bricks\n"));
+        assert!(out.contains("0 safe: []"));
+    }
+
+    #[test]
+    fn check_day22_obj() {
+        let stack = Stack::new(&Day22::parse_input(TEST_INPUT));
+        let mut out = Vec::new();
+        stack.write_obj(&mut out).unwrap();
+        let obj = String::from_utf8(out).unwrap();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("o ")).count(), 7);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 7 * 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 7 * 6);
+        // the bottom brick holds everything up, so it is the reddest
+        assert!(obj.contains("v 1 0 1 1.000 0.200 0.000"));
+    }
 }
@@ -1,9 +1,206 @@
+use crate::bitset::BitSet;
 use crate::Solution;
-use itertools::Itertools;
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 pub struct Day23;
 
+/// Each junction with the junctions one corridor away and the steps to get there.
+pub type Graph = HashMap<(usize, usize), HashSet<((usize, usize), usize)>>;
+
+/// A route length and the junctions along it.
+type Route = (usize, Vec<usize>);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Solve small leftover regions of the graph once per junction and reuse the answer.
+    pub memoize: bool,
+    /// Skip branches that can't beat the longest route found so far.
+    pub prune: bool,
+    /// Number of threads to share the search between.
+    pub threads: usize,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        SearchOptions {
+            memoize: true,
+            prune: true,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hike {
+    pub length: usize,
+    /// Every junction on the way, including the start and the end.
+    pub route: Vec<(usize, usize)>,
+}
+
+/// The junction graph with junctions numbered in position order.
+struct Junctions {
+    names: Vec<(usize, usize)>,
+    edges: Vec<Vec<(usize, usize)>>,
+    /// The longest edge at each junction, as an upper bound on the steps spent getting there.
+    longest_edge: Vec<usize>,
+    start: usize,
+    end: usize,
+}
+
+impl Junctions {
+    fn new(adj: &Graph, start: (usize, usize), end: (usize, usize)) -> Self {
+        let mut names = adj.keys().copied().collect::<Vec<_>>();
+        names.sort();
+        let index = |name: &(usize, usize)| names.binary_search(name).unwrap();
+        let edges = names
+            .iter()
+            .map(|name| {
+                let mut edges = adj[name]
+                    .iter()
+                    .map(|(dst, steps)| (index(dst), *steps))
+                    .collect::<Vec<_>>();
+                edges.sort();
+                edges
+            })
+            .collect::<Vec<Vec<_>>>();
+        let longest_edge = edges
+            .iter()
+            .map(|edges| edges.iter().map(|&(_, steps)| steps).max().unwrap_or(0))
+            .collect();
+        Junctions {
+            start: index(&start),
+            end: index(&end),
+            names,
+            edges,
+            longest_edge,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// The junctions reachable from `from` (not counting `from` itself) through `usable` ones.
+    fn reach(&self, from: usize, usable: impl Fn(usize) -> bool) -> BitSet {
+        let mut seen = BitSet::new(self.len());
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            for &(next, _) in self.edges[node].iter() {
+                if next != from && usable(next) && seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        seen
+    }
+}
+
+/// Subproblems with at most this many junctions left are solved exactly and memoized.
+const MEMO_LIMIT: usize = 24;
+
+/// One thread's depth-first search, sharing the best length found with the others.
+struct Search<'a> {
+    junctions: &'a Junctions,
+    options: SearchOptions,
+    best: &'a AtomicUsize,
+    /// Longest way from a junction to the end through exactly the given region,
+    /// as its length and the junctions after the first.
+    memo: HashMap<(usize, BitSet), Option<Route>>,
+    found: Option<Route>,
+}
+
+impl<'a> Search<'a> {
+    fn new(junctions: &'a Junctions, options: SearchOptions, best: &'a AtomicUsize) -> Self {
+        Search {
+            junctions,
+            options,
+            best,
+            memo: HashMap::new(),
+            found: None,
+        }
+    }
+
+    fn record(&mut self, length: usize, route: Vec<usize>) {
+        if self.found.as_ref().is_none_or(|(best, _)| length > *best) {
+            self.best.fetch_max(length, Ordering::Relaxed);
+            self.found = Some((length, route));
+        }
+    }
+
+    fn from(&mut self, node: usize, length: usize, visited: &mut BitSet, route: &mut Vec<usize>) {
+        let end = self.junctions.end;
+        if node == end {
+            self.record(length, route.clone());
+            return;
+        }
+        let region = self.junctions.reach(node, |i| !visited.contains(i));
+        if !region.contains(end) {
+            return;
+        }
+        if self.options.prune {
+            let bound = region
+                .iter()
+                .map(|i| self.junctions.longest_edge[i])
+                .sum::<usize>();
+            if length + bound <= self.best.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+        if self.options.memoize && region.len() <= MEMO_LIMIT {
+            if let Some((rest, tail)) = self.exact(node, region) {
+                let mut route = route.clone();
+                route.extend(tail);
+                self.record(length + rest, route);
+            }
+            return;
+        }
+        for &(next, steps) in self.junctions.edges[node].iter() {
+            if visited.insert(next) {
+                route.push(next);
+                self.from(next, length + steps, visited, route);
+                route.pop();
+                visited.remove(next);
+            }
+        }
+    }
+
+    /// The longest way from `node` to the end using only junctions in `region`.
+    fn exact(&mut self, node: usize, region: BitSet) -> Option<Route> {
+        let end = self.junctions.end;
+        if node == end {
+            return Some((0, vec![]));
+        }
+        if let Some(known) = self.memo.get(&(node, region.clone())) {
+            return known.clone();
+        }
+        let mut best: Option<Route> = None;
+        for &(next, steps) in self.junctions.edges[node].iter() {
+            if !region.contains(next) {
+                continue;
+            }
+            // only what is still reachable from `next` matters from here on
+            let rest = self
+                .junctions
+                .reach(next, |i| i != node && region.contains(i));
+            if next != end && !rest.contains(end) {
+                continue;
+            }
+            if let Some((length, tail)) = self.exact(next, rest) {
+                if best.as_ref().is_none_or(|(b, _)| steps + length > *b) {
+                    let mut route = vec![next];
+                    route.extend(tail);
+                    best = Some((steps + length, route));
+                }
+            }
+        }
+        self.memo.insert((node, region), best.clone());
+        best
+    }
+}
+
 impl Day23 {
     fn find_start(grid: &Vec<Vec<char>>) -> (usize, usize) {
         (0, grid[0].iter().position(|&p| p == '.').unwrap())
@@ -60,7 +257,7 @@ impl Day23 {
         longest
     }
 
-    fn graph(grid: &Vec<Vec<char>>) -> HashMap<(usize, usize), HashSet<((usize, usize), usize)>> {
+    fn graph(grid: &Vec<Vec<char>>) -> Graph {
         let (rows, cols) = (grid.len(), grid[0].len());
         let (irows, icols) = (rows as isize, cols as isize);
         let start = Self::find_start(grid);
@@ -115,38 +312,69 @@ impl Day23 {
         adj
     }
 
-    fn longest_path_length(
-        adj: &HashMap<(usize, usize), HashSet<((usize, usize), usize)>>,
+    /// The longest route from `start` to `end` through the junction graph
+    /// that never visits a junction twice, or `None` if `end` can't be reached.
+    pub fn longest_route(
+        adj: &Graph,
         start: (usize, usize),
         end: (usize, usize),
-    ) -> usize {
-        let &(start, steps) = adj.get(&start).unwrap().iter().next().unwrap();
-        let mut stack = Vec::from([(start, steps, 0 as u64)]);
-        let mut longest = 0;
-        let mut masks = HashMap::new();
-        for (i, k) in adj.keys().enumerate() {
-            masks.insert(k, 0x1 << i as u64);
-        }
-        while let Some((src, length, mut visited)) = stack.pop() {
-            let Some(edges) = adj.get(&src) else {
-                continue;
-            };
-            let m = masks.get(&src).unwrap();
-            if (visited & m) != 0 {
-                continue;
-            }
-            visited |= m;
-            for &(dst, steps) in edges.iter() {
-                if dst == end {
-                    if length + steps > longest {
-                        longest = length + steps;
+        options: SearchOptions,
+    ) -> Option<Hike> {
+        let junctions = Junctions::new(adj, start, end);
+        let best = AtomicUsize::new(0);
+        let mut visited = BitSet::new(junctions.len());
+        visited.insert(junctions.start);
+        let mut tasks = vec![(junctions.start, 0, visited, vec![junctions.start])];
+        // split the top of the search tree into enough pieces to keep every thread busy
+        while options.threads > 1 && tasks.len() < options.threads * 8 {
+            let mut expanded = Vec::new();
+            for (node, length, visited, route) in tasks.iter() {
+                // a route that has already arrived is finished, but still has to be counted
+                if *node == junctions.end {
+                    expanded.push((*node, *length, visited.clone(), route.clone()));
+                    continue;
+                }
+                for &(next, steps) in junctions.edges[*node].iter() {
+                    if visited.contains(next) {
+                        continue;
                     }
-                    break;
+                    let mut visited = visited.clone();
+                    visited.insert(next);
+                    let mut route = route.clone();
+                    route.push(next);
+                    expanded.push((next, length + steps, visited, route));
                 }
-                stack.push((dst, length + steps, visited));
             }
+            if expanded.len() <= tasks.len() {
+                break;
+            }
+            tasks = expanded;
         }
-        longest
+
+        let tasks = Mutex::new(tasks);
+        let found = thread::scope(|scope| {
+            let workers = (0..options.threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut search = Search::new(&junctions, options, &best);
+                        while let Some((node, length, mut visited, mut route)) =
+                            tasks.lock().unwrap().pop()
+                        {
+                            search.from(node, length, &mut visited, &mut route);
+                        }
+                        search.found
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .filter_map(|worker| worker.join().unwrap())
+                .max_by_key(|&(length, _)| length)
+        });
+        found.map(|(length, route)| Hike {
+            length,
+            route: route.into_iter().map(|i| junctions.names[i]).collect(),
+        })
     }
 }
 
@@ -164,19 +392,17 @@ impl Solution for Day23 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let grid = _parsed_input;
-        let start = Self::find_start(&grid);
-        let end = Self::find_end(&grid);
-        println!("start: {:?}", start);
-        println!("  end: {:?}", end);
-        let adj = Self::graph(&grid);
-
-        println!("adj:");
-        for src in adj.keys().sorted() {
-            let v = adj.get(src).unwrap();
-            println!("{:?}: {:?}", src, v);
-        }
-
-        Self::longest_path_length(&adj, start, end).to_string()
+        let start = Self::find_start(grid);
+        let end = Self::find_end(grid);
+        let adj = Self::graph(grid);
+        let options = SearchOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            ..SearchOptions::default()
+        };
+        Self::longest_route(&adj, start, end, options)
+            .expect("Day 23: no route to the end")
+            .length
+            .to_string()
     }
 }
 
@@ -217,4 +443,107 @@ mod tests {
     fn check_day23_part2_case1() {
         assert_eq!(Day23::solve_part_two(TEST_INPUT), "154".to_string())
     }
+
+    /// A ladder of corridors, `rungs` wide, with a junction wherever a rung meets a rail.
+    fn ladder(rungs: usize) -> String {
+        let width = 2 * rungs + 1;
+        let row = |f: &dyn Fn(usize) -> bool| {
+            (0..width)
+                .map(|c| if f(c) { '.' } else { '#' })
+                .collect::<String>()
+        };
+        [
+            row(&|c| c == 1),
+            row(&|c| c > 0 && c < width - 1),
+            row(&|c| c % 2 == 1),
+            row(&|c| c > 0 && c < width - 1),
+            row(&|c| c == width - 2),
+        ]
+        .join("\n")
+    }
+
+    fn check_route(grid: &Vec<Vec<char>>, hike: &Hike) {
+        let adj = Day23::graph(grid);
+        assert_eq!(hike.route.first(), Some(&Day23::find_start(grid)));
+        assert_eq!(hike.route.last(), Some(&Day23::find_end(grid)));
+        assert_eq!(
+            hike.route.iter().collect::<HashSet<_>>().len(),
+            hike.route.len()
+        );
+        let length = hike
+            .route
+            .windows(2)
+            .map(|w| adj[&w[0]].iter().find(|(dst, _)| *dst == w[1]).unwrap().1)
+            .sum::<usize>();
+        assert_eq!(length, hike.length);
+    }
+
+    #[test]
+    fn check_day23_route() {
+        let grid = Day23::parse_input(TEST_INPUT);
+        let adj = Day23::graph(&grid);
+        let (start, end) = (Day23::find_start(&grid), Day23::find_end(&grid));
+        let hike = Day23::longest_route(&adj, start, end, SearchOptions::default()).unwrap();
+        assert_eq!(hike.length, 154);
+        check_route(&grid, &hike);
+    }
+
+    #[test]
+    fn check_day23_many_junctions() {
+        // the plain search over cells is exact, so use it on a small ladder
+        let small = Day23::parse_input(&ladder(6));
+        let adj = Day23::graph(&small);
+        let (start, end) = (Day23::find_start(&small), Day23::find_end(&small));
+        let expected = Day23::longest_hike_length(&small);
+        for (memoize, prune) in [(false, false), (true, false), (false, true), (true, true)] {
+            let options = SearchOptions {
+                memoize,
+                prune,
+                threads: 1,
+            };
+            let hike = Day23::longest_route(&adj, start, end, options).unwrap();
+            assert_eq!(hike.length, expected);
+            check_route(&small, &hike);
+        }
+
+        let grid = Day23::parse_input(&ladder(40));
+        let adj = Day23::graph(&grid);
+        assert!(adj.len() > 64);
+        let (start, end) = (Day23::find_start(&grid), Day23::find_end(&grid));
+        let sequential = Day23::longest_route(&adj, start, end, SearchOptions::default()).unwrap();
+        check_route(&grid, &sequential);
+        let options = SearchOptions {
+            threads: 4,
+            ..SearchOptions::default()
+        };
+        let parallel = Day23::longest_route(&adj, start, end, options).unwrap();
+        assert_eq!(parallel.length, sequential.length);
+        check_route(&grid, &parallel);
+    }
+
+    #[test]
+    fn check_day23_parallel_short_route() {
+        // the longest route is the direct edge, which arrives while the search is still being split
+        let (start, end, a) = ((0, 1), (9, 9), (1, 1));
+        let mut edges = vec![(start, end, 10), (start, a, 1)];
+        for fork in [(2, 1), (2, 2), (2, 3)] {
+            edges.extend([(a, fork, 1), (fork, end, 1)]);
+        }
+        let mut adj = Graph::new();
+        for (i, j, steps) in edges {
+            adj.entry(i).or_default().insert((j, steps));
+            adj.entry(j).or_default().insert((i, steps));
+        }
+        let sequential = Day23::longest_route(&adj, start, end, SearchOptions::default()).unwrap();
+        assert_eq!(sequential.length, 10);
+        for threads in [2, 4, 8] {
+            let options = SearchOptions {
+                threads,
+                ..SearchOptions::default()
+            };
+            let parallel = Day23::longest_route(&adj, start, end, options).unwrap();
+            assert_eq!(parallel.length, sequential.length);
+            assert_eq!(parallel.route, sequential.route);
+        }
+    }
 }
@@ -1,6 +1,7 @@
+use crate::linalg::{self, LinearSystemError};
 use crate::Solution;
 use itertools::Itertools;
-use nalgebra::{Matrix3, Vector3};
+use num::{BigInt, ToPrimitive};
 
 pub struct Day24;
 
@@ -83,104 +84,112 @@ impl Day24 {
         count
     }
 
-    fn exterior3(u: (i128, i128, i128), v: (i128, i128, i128), w: (i128, i128, i128)) -> i128 {
-        // like the scalar triple product
-        (u.0 * v.1 * w.2) + (u.1 * v.2 * w.0) + (u.2 * v.0 * w.1)
-            - (u.0 * v.2 * w.1)
-            - (u.1 * v.0 * w.2)
-            - (u.2 * v.1 * w.0)
+    fn sub(u: (i128, i128, i128), v: (i128, i128, i128)) -> (i128, i128, i128) {
+        (u.0 - v.0, u.1 - v.1, u.2 - v.2)
     }
 
-    fn exterior2(u: (i128, i128, i128), v: (i128, i128, i128)) -> (i128, i128, i128) {
-        // like the cross product
+    fn cross(u: (i128, i128, i128), v: (i128, i128, i128)) -> (i128, i128, i128) {
         (
-            u.0 * v.1 - u.1 * v.0,
             u.1 * v.2 - u.2 * v.1,
             u.2 * v.0 - u.0 * v.2,
+            u.0 * v.1 - u.1 * v.0,
         )
     }
 
-    fn sub(u: (i128, i128, i128), v: (i128, i128, i128)) -> (i128, i128, i128) {
-        (u.0 - v.0, u.1 - v.1, u.2 - v.2)
-    }
-
-    fn calculate_rock_position(hailstones: &Vec<Hailstone>) -> (i128, i128, i128) {
-        // i gave up on this one, so this is not my solution:
-        // https://www.reddit.com/r/adventofcode/comments/18qgf10/2023_day_24_part_2_solved_as_a_3x3_linear_system/
-        //
+    fn rock_equations(a: Hailstone, b: Hailstone) -> [([i128; 6], i128); 3] {
         // where Pi and Vi are the position and velocity of hailstone i
-        // and P and V are the position and velocity of the rock
-        // Pi + Vi * ti = P + V * ti
+        // and P and V are the position and velocity of the rock,
+        // (Pi - P) and (Vi - V) are parallel, so (Pi - P) x (Vi - V) = 0
         // =>
-        // (Pi - P) - (V - Vi) * ti = 0
-        //
-        // (Pi - P) and (V - Vi) are vectors, and for (Pi - P) and (V - Vi)
-        // to equal a scalar product of one another, they must be parallel,
-        // and wedge products of parallel vectors are zero, so:
-        // (Pi - P) ^ (V - Vi) = 0
+        // (Pi x Vi) - (Pi x V) - (P x Vi) + (P x V) = 0
         //
-        // multiply it out
-        // (Pi ^ V) - (P ^ V) - (Pi ^ Vi) + (P ^ Vi) = 0
+        // P x V is the same for every hailstone, so subtract the equation for hailstone j
+        // (Pi x Vi) - (Pj x Vj) = ((Pi - Pj) x V) + (P x (Vi - Vj))
         //
-        // remove (P ^ V) by subtracting with the equation for hailstone j
-        // ((Pi - Pj) ^ V) - (Pi ^ Vi) + (Pj ^ Vj) + (P ^ (Vi - Vj)) = 0
-        //
-        // get rid of ((Pi - Pj) ^ V) (because we don't care about the velocity)
-        // since u ^ v ^ u = 0 (since u is parallel to itself)
-        // =>
-        // (P ^ (Vi - Vj) ^ (Pi - Pj)) + Pi ^ Vi ^ Pj + Pj ^ Vj ^ Pi = 0
-        //
-        // 3 unknowns (the 3 dimensions of P) so we need 3 pairs
-        let mut equations = Vec::new();
-        for pair in (0..3).into_iter().combinations(2) {
-            let &[i, j] = pair.as_slice() else {
-                continue;
-            };
-            let (pi, vi, pj, vj) = (
-                hailstones[i].position,
-                hailstones[i].velocity,
-                hailstones[j].position,
-                hailstones[j].velocity,
-            );
-            let (a, b, c) = Self::exterior2(Self::sub(vi, vj), Self::sub(pi, pj));
-            let d = -(Self::exterior3(pi, vi, pj) + Self::exterior3(pj, vj, pi));
-            let equation = (a, b, c, d); // ax + by + cz = d
-            println!(
-                "{:?}x + {:?}y + {:?}z = {:?}",
-                equation.0, equation.1, equation.2, equation.3
-            );
-            equations.push(equation);
-        }
-        // then i actually just plugged these into wolfram alpha:
-        // https://www.wolframalpha.com/input?i=system+equation+calculator
-
-        // solve system of equations
-        let abc = Matrix3::new(
-            equations[0].0 as f64,
-            equations[0].1 as f64,
-            equations[0].2 as f64,
-            equations[1].0 as f64,
-            equations[1].1 as f64,
-            equations[1].2 as f64,
-            equations[2].0 as f64,
-            equations[2].1 as f64,
-            equations[2].2 as f64,
-        );
-        let d = Vector3::new(
-            equations[0].3 as f64,
-            equations[1].3 as f64,
-            equations[2].3 as f64,
+        // which is linear in the six unknowns (P, V)
+        let (dp, dv) = (
+            Self::sub(a.position, b.position),
+            Self::sub(a.velocity, b.velocity),
         );
-        let p: Vector3<f64> = abc.qr().solve(&d).unwrap();
-        println!("x={:?} y={:?} z={:?} (as f64s)", p.x, p.y, p.z);
-        // despite floating point error, we still get the right answer
-        let (x, y, z) = (
-            p.x.round() as i128,
-            p.y.round() as i128,
-            p.z.round() as i128,
+        let rhs = Self::sub(
+            Self::cross(a.position, a.velocity),
+            Self::cross(b.position, b.velocity),
         );
-        println!("x={:?} y={:?} z={:?}", x, y, z);
-        (x, y, z)
+        [
+            ([0, dv.2, -dv.1, 0, -dp.2, dp.1], rhs.0),
+            ([-dv.2, 0, dv.0, dp.2, 0, -dp.0], rhs.1),
+            ([dv.1, -dv.0, 0, -dp.1, dp.0, 0], rhs.2),
+        ]
+    }
+
+    fn rock_from_triple(
+        a: Hailstone,
+        b: Hailstone,
+        c: Hailstone,
+    ) -> Result<Option<Hailstone>, LinearSystemError> {
+        let equations = Self::rock_equations(a, b)
+            .into_iter()
+            .chain(Self::rock_equations(a, c))
+            .collect::<Vec<_>>();
+        let lhs = equations
+            .iter()
+            .map(|(row, _)| row.iter().map(|&v| BigInt::from(v)).collect())
+            .collect::<Vec<Vec<BigInt>>>();
+        let rhs = equations
+            .iter()
+            .map(|&(_, v)| BigInt::from(v))
+            .collect::<Vec<BigInt>>();
+        let solution = linalg::solve(&lhs, &rhs)?;
+        // the rock has to start at whole-number coordinates
+        let Some(values) = solution
+            .iter()
+            .map(|v| v.is_integer().then(|| v.to_integer().to_i128()).flatten())
+            .collect::<Option<Vec<i128>>>()
+        else {
+            return Ok(None);
+        };
+        Ok(Some(Hailstone {
+            position: (values[0], values[1], values[2]),
+            velocity: (values[3], values[4], values[5]),
+        }))
+    }
+
+    /// The time at which `rock` hits `hailstone`, if it ever does.
+    fn collision_time(rock: Hailstone, hailstone: Hailstone) -> Option<i128> {
+        // P + V * t = Pi + Vi * t  =>  (P - Pi) = (Vi - V) * t in every dimension
+        let dp = Self::sub(rock.position, hailstone.position);
+        let dv = Self::sub(hailstone.velocity, rock.velocity);
+        let mut time = None;
+        for (p, v) in [(dp.0, dv.0), (dp.1, dv.1), (dp.2, dv.2)] {
+            if v == 0 {
+                if p != 0 {
+                    return None;
+                }
+            } else if p % v != 0 || time.is_some_and(|t| t != p / v) {
+                return None;
+            } else {
+                time = Some(p / v);
+            }
+        }
+        let t = time.unwrap_or(0);
+        (t >= 0).then_some(t)
+    }
+
+    fn find_rock(hailstones: &[Hailstone]) -> Option<Hailstone> {
+        // any three hailstones pin down the rock unless their equations are degenerate
+        for (&a, &b, &c) in hailstones.iter().tuple_combinations() {
+            match Self::rock_from_triple(a, b, c) {
+                Err(_) => continue,
+                Ok(rock) => {
+                    return rock.filter(|&rock| {
+                        hailstones
+                            .iter()
+                            .all(|&h| Self::collision_time(rock, h).is_some())
+                    });
+                }
+            }
+        }
+        None
     }
 }
 
@@ -221,7 +230,8 @@ impl Solution for Day24 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let hailstones = _parsed_input;
-        let (x, y, z) = Self::calculate_rock_position(&hailstones);
+        let rock = Self::find_rock(hailstones).expect("Day 24: no rock hits every hailstone");
+        let (x, y, z) = rock.position;
         (x + y + z).to_string()
     }
 }
@@ -246,4 +256,59 @@ mod tests {
     fn check_day24_part2_case1() {
         assert_eq!(Day24::solve_part_two(TEST_INPUT), "47".to_string())
     }
+
+    #[test]
+    fn check_day24_rock_trajectory() {
+        let hailstones = Day24::parse_input(TEST_INPUT);
+        let rock = Day24::find_rock(&hailstones).unwrap();
+        assert_eq!(rock.position, (24, 13, 10));
+        assert_eq!(rock.velocity, (-3, 1, 2));
+        let times = hailstones
+            .iter()
+            .map(|&h| Day24::collision_time(rock, h))
+            .collect::<Vec<_>>();
+        assert_eq!(times, vec![Some(5), Some(3), Some(4), Some(6), Some(1)]);
+    }
+
+    #[test]
+    fn check_day24_large_coordinates() {
+        // big enough that f64 can't represent the intermediate products exactly
+        let rock = Hailstone {
+            position: (187016878804004, 175507140888229, 181140900341847),
+            velocity: (192, 210, 179),
+        };
+        let hailstones = [
+            (-73, 9, 41, 593847583927),
+            (301, -122, 87, 402918374651),
+            (-15, -308, 160, 982734019283),
+            (44, 77, -201, 123456789012),
+            (-250, 190, 12, 777777777777),
+        ]
+        .map(|(vx, vy, vz, t): (i128, i128, i128, i128)| {
+            let (px, py, pz) = rock.position;
+            let (rx, ry, rz) = rock.velocity;
+            Hailstone {
+                position: (px + (rx - vx) * t, py + (ry - vy) * t, pz + (rz - vz) * t),
+                velocity: (vx, vy, vz),
+            }
+        });
+        let found = Day24::find_rock(&hailstones).unwrap();
+        assert_eq!(found.position, rock.position);
+        assert_eq!(found.velocity, rock.velocity);
+        assert_eq!(
+            Day24::collision_time(found, hailstones[2]),
+            Some(982734019283)
+        );
+    }
+
+    #[test]
+    fn check_day24_degenerate_hailstones() {
+        // all parallel hailstones can't pin the rock down
+        let hailstones = Day24::parse_input(
+            "0, 0, 0 @ 1, 1, 1
+1, 0, 0 @ 1, 1, 1
+0, 1, 0 @ 1, 1, 1",
+        );
+        assert!(Day24::find_rock(&hailstones).is_none());
+    }
 }
@@ -1,35 +1,36 @@
+use crate::mincut::{self, MinCut};
 use crate::Solution;
+use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
 
 pub struct Day25;
 
 impl Day25 {
-    fn count(
-        v: &String,
-        graph: &HashMap<String, HashSet<String>>,
-        vertices: &HashSet<String>,
-    ) -> usize {
-        let neighbors = graph.get(v).unwrap();
-        neighbors.difference(vertices).count()
+    fn min_cut(graph: &HashMap<String, HashSet<String>>) -> (MinCut, Vec<&str>) {
+        let names = graph
+            .keys()
+            .map(|s| s.as_str())
+            .sorted()
+            .collect::<Vec<&str>>();
+        let index = names
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| (name, i))
+            .collect::<HashMap<&str, usize>>();
+        let edges = graph
+            .iter()
+            .flat_map(|(u, vs)| vs.iter().map(move |v| (u, v)))
+            .filter(|(u, v)| u < v)
+            .map(|(u, v)| (index[u.as_str()], index[v.as_str()], 1))
+            .collect::<Vec<_>>();
+        let cut = mincut::stoer_wagner(names.len(), &edges)
+            .expect("Day 25: need at least two components");
+        (cut, names)
     }
 
     fn find_component_sizes(graph: &HashMap<String, HashSet<String>>) -> (usize, usize) {
-        // from https://www.reddit.com/r/adventofcode/comments/18qbsxs/comment/ketzp94/
-        let mut vertices = graph.keys().map(|s| s.clone()).collect::<HashSet<String>>();
-        while vertices
-            .iter()
-            .map(|v| Self::count(v, graph, &vertices))
-            .sum::<usize>()
-            != 3
-        {
-            let (_, v) = vertices
-                .iter()
-                .map(|v| (Self::count(v, graph, &vertices), v))
-                .max()
-                .unwrap();
-            vertices.remove(&(v.clone()));
-        }
-        (vertices.len(), graph.keys().count() - vertices.len())
+        let (cut, _) = Self::min_cut(graph);
+        (cut.partition.0.len(), cut.partition.1.len())
     }
 }
 
@@ -85,4 +86,18 @@ frs: qnr lhk lsr";
     fn check_day25_part1_case1() {
         assert_eq!(Day25::solve_part_one(TEST_INPUT), "54".to_string())
     }
+
+    #[test]
+    fn check_day25_cut_edges() {
+        let graph = Day25::parse_input(TEST_INPUT);
+        let (cut, names) = Day25::min_cut(&graph);
+        assert_eq!(cut.weight, 3);
+        let wires = cut
+            .edges
+            .iter()
+            .map(|&(u, v)| (names[u], names[v]))
+            .sorted()
+            .collect::<Vec<_>>();
+        assert_eq!(wires, vec![("bvb", "cmg"), ("hfx", "pzl"), ("jqt", "nvd")]);
+    }
 }
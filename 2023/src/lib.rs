@@ -1,3 +1,6 @@
+pub mod aho_corasick;
+pub mod bitset;
+pub mod cycles;
 pub mod day01;
 pub mod day02;
 pub mod day03;
@@ -23,6 +26,11 @@ pub mod day22;
 pub mod day23;
 pub mod day24;
 pub mod day25;
+pub mod linalg;
+pub mod mincut;
+pub mod search;
+
+pub use common::{intervals, parse, polygon};
 
 pub trait Solution {
     type ParsedInput;
@@ -69,33 +77,65 @@ pub trait Solution {
     }
 }
 
+/// The puzzle input for `day`.
+pub fn input(day: &i32) -> &'static str {
+    match day {
+        1 => include_str!("../inputs/01"),
+        2 => include_str!("../inputs/02"),
+        3 => include_str!("../inputs/03"),
+        4 => include_str!("../inputs/04"),
+        5 => include_str!("../inputs/05"),
+        6 => include_str!("../inputs/06"),
+        7 => include_str!("../inputs/07"),
+        8 => include_str!("../inputs/08"),
+        9 => include_str!("../inputs/09"),
+        10 => include_str!("../inputs/10"),
+        11 => include_str!("../inputs/11"),
+        12 => include_str!("../inputs/12"),
+        13 => include_str!("../inputs/13"),
+        14 => include_str!("../inputs/14"),
+        15 => include_str!("../inputs/15"),
+        16 => include_str!("../inputs/16"),
+        17 => include_str!("../inputs/17"),
+        18 => include_str!("../inputs/18"),
+        19 => include_str!("../inputs/19"),
+        20 => include_str!("../inputs/20"),
+        21 => include_str!("../inputs/21"),
+        22 => include_str!("../inputs/22"),
+        23 => include_str!("../inputs/23"),
+        24 => include_str!("../inputs/24"),
+        25 => include_str!("../inputs/25"),
+        _ => panic!("Day not found"),
+    }
+}
+
 pub fn solve_day(day: &i32, include_time: bool) {
     match day {
-        1 => day01::Day01::solve(include_str!("../inputs/01"), include_time),
-        2 => day02::Day02::solve(include_str!("../inputs/02"), include_time),
-        3 => day03::Day03::solve(include_str!("../inputs/03"), include_time),
-        4 => day04::Day04::solve(include_str!("../inputs/04"), include_time),
-        5 => day05::Day05::solve(include_str!("../inputs/05"), include_time),
-        6 => day06::Day06::solve(include_str!("../inputs/06"), include_time),
-        7 => day07::Day07::solve(include_str!("../inputs/07"), include_time),
-        8 => day08::Day08::solve(include_str!("../inputs/08"), include_time),
-        9 => day09::Day09::solve(include_str!("../inputs/09"), include_time),
-        10 => day10::Day10::solve(include_str!("../inputs/10"), include_time),
-        11 => day11::Day11::solve(include_str!("../inputs/11"), include_time),
-        12 => day12::Day12::solve(include_str!("../inputs/12"), include_time),
-        13 => day13::Day13::solve(include_str!("../inputs/13"), include_time),
-        14 => day14::Day14::solve(include_str!("../inputs/14"), include_time),
-        15 => day15::Day15::solve(include_str!("../inputs/15"), include_time),
-        16 => day16::Day16::solve(include_str!("../inputs/16"), include_time),
-        17 => day17::Day17::solve(include_str!("../inputs/17"), include_time),
-        18 => day18::Day18::solve(include_str!("../inputs/18"), include_time),
-        19 => day19::Day19::solve(include_str!("../inputs/19"), include_time),
-        20 => day20::Day20::solve(include_str!("../inputs/20"), include_time),
-        21 => day21::Day21::solve(include_str!("../inputs/21"), include_time),
-        22 => day22::Day22::solve(include_str!("../inputs/22"), include_time),
-        23 => day23::Day23::solve(include_str!("../inputs/23"), include_time),
-        24 => day24::Day24::solve(include_str!("../inputs/24"), include_time),
-        25 => day25::Day25::solve(include_str!("../inputs/25"), include_time),
+        1 => day01::Day01::solve(input(day), include_time),
+        2 => day02::Day02::solve(input(day), include_time),
+        3 => day03::Day03::solve(input(day), include_time),
+        4 => day04::Day04::solve(input(day), include_time),
+        5 => day05::Day05::solve(input(day), include_time),
+        6 => day06::Day06::solve(input(day), include_time),
+        7 => day07::Day07::solve(input(day), include_time),
+        8 => day08::Day08::solve(input(day), include_time),
+        9 => day09::Day09::solve(input(day), include_time),
+        10 => day10::Day10::solve(input(day), include_time),
+        11 => day11::Day11::solve(input(day), include_time),
+        12 => day12::Day12::solve(input(day), include_time),
+        13 => day13::Day13::solve(input(day), include_time),
+        14 => day14::Day14::solve(input(day), include_time),
+        15 => day15::Day15::solve(input(day), include_time),
+        16 => day16::Day16::solve(input(day), include_time),
+        17 => day17::Day17::solve(input(day), include_time),
+        18 => day18::Day18::solve(input(day), include_time),
+        19 => day19::Day19::solve(input(day), include_time),
+        20 => day20::Day20::solve(input(day), include_time),
+        21 => day21::Day21::solve(input(day), include_time),
+        22 => day22::Day22::solve(input(day), include_time),
+        23 => day23::Day23::solve(input(day), include_time),
+        24 => day24::Day24::solve(input(day), include_time),
+        25 => day25::Day25::solve(input(day), include_time),
         _ => panic!("Day not found"),
     };
 }
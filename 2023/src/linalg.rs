@@ -0,0 +1,106 @@
+use num::{BigInt, BigRational, One, Zero};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinearSystemError {
+    /// The equations don't pin down every unknown.
+    Singular,
+    /// The equations contradict each other.
+    Inconsistent,
+}
+
+/// Solve `a x = b` exactly, for `a` with at least as many rows as columns.
+/// Uses fraction-free (Bareiss) elimination so every intermediate value stays an integer,
+/// and only the final back substitution needs fractions.
+pub fn solve(a: &[Vec<BigInt>], b: &[BigInt]) -> Result<Vec<BigRational>, LinearSystemError> {
+    let (rows, cols) = (a.len(), a.first().map_or(0, |row| row.len()));
+    assert_eq!(rows, b.len(), "need one right-hand side per equation");
+    let mut m = a
+        .iter()
+        .zip(b.iter())
+        .map(|(row, rhs)| {
+            assert_eq!(
+                row.len(),
+                cols,
+                "every equation needs the same number of unknowns"
+            );
+            row.iter().chain([rhs]).cloned().collect::<Vec<BigInt>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut pivots = Vec::new();
+    let mut prev = BigInt::one();
+    for col in 0..cols {
+        let r = pivots.len();
+        let Some(p) = (r..rows).find(|&i| !m[i][col].is_zero()) else {
+            continue;
+        };
+        m.swap(r, p);
+        for i in (r + 1)..rows {
+            for j in (col + 1)..=cols {
+                let value = (&m[r][col] * &m[i][j] - &m[i][col] * &m[r][j]) / &prev;
+                m[i][j] = value;
+            }
+            m[i][col] = BigInt::zero();
+        }
+        prev = m[r][col].clone();
+        pivots.push(col);
+    }
+
+    let rank = pivots.len();
+    if m[rank..].iter().any(|row| !row[cols].is_zero()) {
+        return Err(LinearSystemError::Inconsistent);
+    }
+    if rank < cols {
+        return Err(LinearSystemError::Singular);
+    }
+
+    let mut x = vec![BigRational::zero(); cols];
+    for r in (0..rank).rev() {
+        let mut sum = BigRational::from_integer(m[r][cols].clone());
+        for j in (r + 1)..cols {
+            sum -= &x[j] * BigRational::from_integer(m[r][j].clone());
+        }
+        x[r] = sum / BigRational::from_integer(m[r][r].clone());
+    }
+    Ok(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system(a: &[&[i64]], b: &[i64]) -> (Vec<Vec<BigInt>>, Vec<BigInt>) {
+        (
+            a.iter()
+                .map(|row| row.iter().map(|&v| BigInt::from(v)).collect())
+                .collect(),
+            b.iter().map(|&v| BigInt::from(v)).collect(),
+        )
+    }
+
+    #[test]
+    fn check_linalg_solve() {
+        let (a, b) = system(&[&[0, 2, 1], &[1, -2, -3], &[-1, 1, 2]], &[-8, 0, 3]);
+        let x = solve(&a, &b).unwrap();
+        let expected = [-4, -5, 2].map(|v| BigRational::from_integer(BigInt::from(v)));
+        assert_eq!(x, expected);
+
+        let (a, b) = system(&[&[2, 1], &[1, 3]], &[1, 1]);
+        let x = solve(&a, &b).unwrap();
+        let fifth = |n: i64| BigRational::new(BigInt::from(n), BigInt::from(5));
+        assert_eq!(x, vec![fifth(2), fifth(1)]);
+    }
+
+    #[test]
+    fn check_linalg_degenerate() {
+        let (a, b) = system(&[&[1, 2], &[2, 4]], &[3, 6]);
+        assert_eq!(solve(&a, &b), Err(LinearSystemError::Singular));
+        let (a, b) = system(&[&[1, 2], &[2, 4]], &[3, 7]);
+        assert_eq!(solve(&a, &b), Err(LinearSystemError::Inconsistent));
+        // overdetermined but consistent
+        let (a, b) = system(&[&[1, 0], &[0, 1], &[1, 1]], &[2, 3, 5]);
+        assert!(solve(&a, &b).is_ok());
+        let (a, b) = system(&[&[1, 0], &[0, 1], &[1, 1]], &[2, 3, 6]);
+        assert_eq!(solve(&a, &b), Err(LinearSystemError::Inconsistent));
+    }
+}
@@ -1,14 +1,285 @@
-use aoc2023::solve_day;
-use clap::Parser;
+use aoc2023::day05::Day05;
+use aoc2023::day08::Day08;
+use aoc2023::day10::Day10;
+use aoc2023::day14::Day14;
+use aoc2023::day16::{Beam, Day16};
+use aoc2023::day20::{Day20, TraceFilter};
+use aoc2023::day21::Day21;
+use aoc2023::day22::{Day22, Stack};
+use aoc2023::intervals::{Interval, IntervalSet};
+use aoc2023::{input, solve_day, Solution};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::PathBuf;
 
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
     /// Selects a single day to run. If not specified, all days are run.
     day: Option<i32>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Level {
+    Low,
+    High,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum Heading {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Day 5: check the almanac's maps and print them composed into one.
+    Day05Pipeline {
+        /// Also print the seeds that end up at this location.
+        #[arg(long)]
+        location: Option<u64>,
+        /// Read the almanac from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 8: print where each ghost starts walking in circles, and how often.
+    Day08Cycles {
+        /// Read the network from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 10: redraw the main loop, marking the tiles inside and outside it.
+    Day10Render {
+        /// Read the pipes from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 14: print the period of the platform under spin cycles.
+    Day14Cycles {
+        /// Read the platform from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 16: draw the path of one beam through the contraption.
+    Day16Beam {
+        #[arg(long, default_value_t = 0)]
+        row: usize,
+        #[arg(long, default_value_t = 0)]
+        col: usize,
+        #[arg(long, value_enum, default_value_t = Heading::Right)]
+        heading: Heading,
+        /// Mark energized tiles with `#` instead of drawing the beam's directions.
+        #[arg(long)]
+        energized: bool,
+        /// Read the contraption from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 16: print how often each tile is energized over all entry beams.
+    Day16Heatmap {
+        /// Also write the heatmap to this file as a PPM image.
+        #[arg(long)]
+        ppm: Option<PathBuf>,
+        /// Read the contraption from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 20: record the pulses sent on each button press.
+    Day20Trace {
+        /// Where to write the trace.
+        output: PathBuf,
+        #[arg(long, default_value_t = 1)]
+        presses: usize,
+        /// Only keep pulses to or from this module. May be repeated.
+        #[arg(long = "module")]
+        modules: Vec<String>,
+        /// Only keep pulses of this level.
+        #[arg(long)]
+        level: Option<Level>,
+        /// Read the modules from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 20: step through button presses one pulse at a time.
+    Day20Step {
+        /// Read the modules from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 20: print the period of each subcircuit feeding the target module.
+    Day20Cycles {
+        #[arg(long, default_value = "rx")]
+        target: String,
+        /// Read the modules from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 21: print reachable plot counts for a range of step counts,
+    /// with the garden's parity split and the fitted quadratic growth.
+    Day21Series {
+        #[arg(long, default_value_t = 0)]
+        from: usize,
+        #[arg(long, default_value_t = 200)]
+        to: usize,
+        /// Read the garden from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 22: answer queries about the settled stack of bricks.
+    Day22Inspect {
+        /// Read the bricks from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Day 22: write the settled stack as a Wavefront OBJ mesh.
+    Day22Export {
+        /// Where to write the mesh.
+        output: PathBuf,
+        /// Read the bricks from this file instead of the puzzle input.
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+}
+
+fn read_input(day: i32, path: Option<PathBuf>) -> String {
+    match path {
+        Some(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("couldn't read {}: {}", path.display(), e)),
+        None => input(&day).to_string(),
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
+    if let Some(command) = cli.command {
+        match command {
+            Command::Day05Pipeline { location, input } => {
+                let almanac = Day05::parse_input(&read_input(5, input));
+                for check in Day05::check_maps(&almanac) {
+                    println!("{}", check);
+                }
+                print!("{}", Day05::pipeline(&almanac));
+                if let Some(location) = location {
+                    let locations = IntervalSet::from_iter([Interval::new(location, location)]);
+                    for seeds in Day05::seeds_for(&almanac, &locations).iter() {
+                        println!("seeds {}..={} reach {}", seeds.lo, seeds.hi, location);
+                    }
+                }
+            }
+            Command::Day08Cycles { input } => {
+                print!("{}", Day08::cycle_report(&read_input(8, input)));
+            }
+            Command::Day10Render { input } => {
+                let pipes = Day10::parse_input(&read_input(10, input));
+                println!("{}", Day10::render(&pipes));
+            }
+            Command::Day14Cycles { input } => {
+                print!("{}", Day14::cycle_report(&read_input(14, input)));
+            }
+            Command::Day16Beam {
+                row,
+                col,
+                heading,
+                energized,
+                input,
+            } => {
+                let grid = Day16::parse_input(&read_input(16, input));
+                let (rows, cols) = (grid.len(), grid.first().map_or(0, |r| r.len()));
+                if row >= rows || col >= cols {
+                    let mut cli = Cli::command();
+                    cli.build();
+                    cli.find_subcommand_mut("day16-beam")
+                        .unwrap()
+                        .error(
+                            ErrorKind::ValueValidation,
+                            format!(
+                                "--row {} --col {} is outside the {}x{} contraption",
+                                row, col, rows, cols
+                            ),
+                        )
+                        .exit();
+                }
+                let (dr, dc) = match heading {
+                    Heading::Up => (-1, 0),
+                    Heading::Down => (1, 0),
+                    Heading::Left => (0, -1),
+                    Heading::Right => (0, 1),
+                };
+                let beam = Beam::new(row, col, dr, dc);
+                if energized {
+                    println!("{}", Day16::render_energized(&grid, beam));
+                } else {
+                    println!("{}", Day16::render_beam(&grid, beam));
+                }
+            }
+            Command::Day16Heatmap { ppm, input } => {
+                let grid = Day16::parse_input(&read_input(16, input));
+                let counts = Day16::heatmap(&grid);
+                println!("{}", Day16::heatmap_text(&counts));
+                if let Some(ppm) = ppm {
+                    let file = File::create(&ppm)
+                        .unwrap_or_else(|e| panic!("couldn't create {}: {}", ppm.display(), e));
+                    Day16::write_heatmap_ppm(&mut BufWriter::new(file), &counts)
+                        .unwrap_or_else(|e| panic!("couldn't write {}: {}", ppm.display(), e));
+                }
+            }
+            Command::Day20Trace {
+                output,
+                presses,
+                modules,
+                level,
+                input,
+            } => {
+                let (mut states, dests) = Day20::parse_input(&read_input(20, input));
+                let filter = TraceFilter {
+                    modules,
+                    level: level.map(|level| matches!(level, Level::High)),
+                };
+                let trace = Day20::trace(&mut states, &dests, presses, &filter);
+                let file = File::create(&output)
+                    .unwrap_or_else(|e| panic!("couldn't create {}: {}", output.display(), e));
+                Day20::write_trace(&mut BufWriter::new(file), &trace)
+                    .unwrap_or_else(|e| panic!("couldn't write {}: {}", output.display(), e));
+            }
+            Command::Day20Step { input } => {
+                let (mut states, dests) = Day20::parse_input(&read_input(20, input));
+                Day20::step_interactively(
+                    &mut states,
+                    &dests,
+                    io::stdin().lock(),
+                    &mut io::stdout(),
+                )
+                .unwrap_or_else(|e| panic!("Day 20: {}", e));
+            }
+            Command::Day20Cycles { target, input } => {
+                print!("{}", Day20::cycle_report(&read_input(20, input), &target));
+            }
+            Command::Day21Series { from, to, input } => {
+                print!("{}", Day21::report(&read_input(21, input), from..=to));
+            }
+            Command::Day22Inspect { input } => {
+                let stack = Stack::new(&Day22::parse_input(&read_input(22, input)));
+                stack
+                    .inspect(io::stdin().lock(), &mut io::stdout())
+                    .unwrap_or_else(|e| panic!("Day 22: {}", e));
+            }
+            Command::Day22Export { output, input } => {
+                let stack = Stack::new(&Day22::parse_input(&read_input(22, input)));
+                let file = File::create(&output)
+                    .unwrap_or_else(|e| panic!("couldn't create {}: {}", output.display(), e));
+                stack
+                    .write_obj(&mut BufWriter::new(file))
+                    .unwrap_or_else(|e| panic!("couldn't write {}: {}", output.display(), e));
+            }
+        }
+        return;
+    }
     let days = (1..=25).collect::<Vec<i32>>();
     let mut days_to_execute = vec![];
     if let Some(day) = cli.day {
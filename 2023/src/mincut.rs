@@ -0,0 +1,129 @@
+use std::collections::{BinaryHeap, HashMap};
+
+/// A global minimum cut of an undirected graph.
+/// The first side of `partition` always contains vertex `0`,
+/// and `edges` are the original edges crossing between the two sides.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MinCut {
+    pub weight: u64,
+    pub partition: (Vec<usize>, Vec<usize>),
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// Stoer–Wagner minimum cut over vertices `0..n` and weighted undirected `edges`.
+/// Returns `None` when there are fewer than two vertices to split.
+pub fn stoer_wagner(n: usize, edges: &[(usize, usize, u64)]) -> Option<MinCut> {
+    if n < 2 {
+        return None;
+    }
+    let mut adj = vec![HashMap::<usize, u64>::new(); n];
+    for &(u, v, w) in edges {
+        if u == v {
+            continue;
+        }
+        *adj[u].entry(v).or_default() += w;
+        *adj[v].entry(u).or_default() += w;
+    }
+    let mut members = (0..n).map(|v| vec![v]).collect::<Vec<_>>();
+    let mut active = (0..n).collect::<Vec<usize>>();
+    let mut best: Option<(u64, Vec<usize>)> = None;
+
+    while active.len() > 1 {
+        // maximum adjacency ordering: repeatedly add the most tightly connected vertex
+        let mut keys = vec![0; n];
+        let mut added = vec![false; n];
+        let mut heap = active.iter().map(|&v| (0, v)).collect::<BinaryHeap<_>>();
+        let (mut s, mut t) = (active[0], active[0]);
+        let mut cut_of_phase = 0;
+        while let Some((key, v)) = heap.pop() {
+            if added[v] || key != keys[v] {
+                continue;
+            }
+            added[v] = true;
+            (s, t) = (t, v);
+            cut_of_phase = key;
+            for (&u, &w) in &adj[v] {
+                if !added[u] {
+                    keys[u] += w;
+                    heap.push((keys[u], u));
+                }
+            }
+        }
+        if best
+            .as_ref()
+            .is_none_or(|(weight, _)| cut_of_phase < *weight)
+        {
+            best = Some((cut_of_phase, members[t].clone()));
+        }
+
+        // merge t into s
+        let t_adj = std::mem::take(&mut adj[t]);
+        for (u, w) in t_adj {
+            adj[u].remove(&t);
+            if u != s {
+                *adj[s].entry(u).or_default() += w;
+                *adj[u].entry(s).or_default() += w;
+            }
+        }
+        let t_members = std::mem::take(&mut members[t]);
+        members[s].extend(t_members);
+        active.retain(|&v| v != t);
+    }
+
+    let (weight, side) = best.unwrap();
+    let mut in_side = vec![false; n];
+    for &v in &side {
+        in_side[v] = true;
+    }
+    let mut one = (0..n).filter(|&v| in_side[v]).collect::<Vec<_>>();
+    let mut other = (0..n).filter(|&v| !in_side[v]).collect::<Vec<_>>();
+    if !in_side[0] {
+        std::mem::swap(&mut one, &mut other);
+    }
+    let edges = edges
+        .iter()
+        .filter(|&&(u, v, _)| in_side[u] != in_side[v])
+        .map(|&(u, v, _)| (u, v))
+        .collect();
+    Some(MinCut {
+        weight,
+        partition: (one, other),
+        edges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_stoer_wagner_weighted() {
+        // the example graph from the Stoer–Wagner paper, vertices renumbered from 0
+        let edges = [
+            (0, 1, 2),
+            (0, 4, 3),
+            (1, 2, 3),
+            (1, 4, 2),
+            (1, 5, 2),
+            (2, 3, 4),
+            (2, 6, 2),
+            (3, 6, 2),
+            (3, 7, 2),
+            (4, 5, 3),
+            (5, 6, 1),
+            (6, 7, 3),
+        ];
+        let cut = stoer_wagner(8, &edges).unwrap();
+        assert_eq!(cut.weight, 4);
+        assert_eq!(cut.partition, (vec![0, 1, 4, 5], vec![2, 3, 6, 7]));
+        assert_eq!(cut.edges, vec![(1, 2), (5, 6)]);
+    }
+
+    #[test]
+    fn check_stoer_wagner_disconnected() {
+        let cut = stoer_wagner(4, &[(0, 1, 1), (2, 3, 5)]).unwrap();
+        assert_eq!(cut.weight, 0);
+        assert!(cut.edges.is_empty());
+        assert_eq!(stoer_wagner(1, &[]), None);
+    }
+}
@@ -0,0 +1,319 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::hash::Hash;
+use std::ops::Add;
+
+/// Distances and predecessors found by one of the searches below.
+/// `goal` is the first goal state settled, if the search was given one.
+#[derive(Clone, Debug)]
+pub struct SearchResult<S, C> {
+    pub dist: HashMap<S, C>,
+    pub pred: HashMap<S, S>,
+    pub goal: Option<S>,
+}
+
+impl<S, C> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy,
+{
+    pub fn distance(&self, state: &S) -> Option<C> {
+        self.dist.get(state).copied()
+    }
+
+    pub fn goal_distance(&self) -> Option<C> {
+        self.goal.as_ref().and_then(|g| self.distance(g))
+    }
+
+    /// Walk the predecessor map back from `state` to one of the sources.
+    pub fn path_to(&self, state: &S) -> Option<Vec<S>> {
+        if !self.dist.contains_key(state) {
+            return None;
+        }
+        let mut path = vec![state.clone()];
+        let mut current = state;
+        while let Some(prev) = self.pred.get(current) {
+            path.push(prev.clone());
+            current = prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    pub fn goal_path(&self) -> Option<Vec<S>> {
+        self.goal.as_ref().and_then(|g| self.path_to(g))
+    }
+}
+
+struct Entry<S, C> {
+    priority: C,
+    cost: C,
+    state: S,
+}
+
+impl<S, C: Ord> Ord for Entry<S, C> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed so that BinaryHeap pops the smallest priority first
+        other
+            .priority
+            .cmp(&self.priority)
+            .then_with(|| self.cost.cmp(&other.cost))
+    }
+}
+
+impl<S, C: Ord> PartialOrd for Entry<S, C> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, C: Ord> PartialEq for Entry<S, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S, C: Ord> Eq for Entry<S, C> {}
+
+/// Dijkstra's algorithm from every state in `starts`.
+/// Stops as soon as a state satisfying `is_goal` is settled.
+pub fn dijkstra<S, C, N, I, G>(
+    starts: impl IntoIterator<Item = S>,
+    neighbours: N,
+    is_goal: G,
+) -> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, C)>,
+    G: FnMut(&S) -> bool,
+{
+    astar(starts, neighbours, |_| C::default(), is_goal)
+}
+
+/// A* search. `heuristic` must never overestimate the remaining cost
+/// (and should be consistent) for the goal distance to be optimal.
+pub fn astar<S, C, N, I, H, G>(
+    starts: impl IntoIterator<Item = S>,
+    mut neighbours: N,
+    mut heuristic: H,
+    mut is_goal: G,
+) -> SearchResult<S, C>
+where
+    S: Clone + Eq + Hash,
+    C: Copy + Ord + Default + Add<Output = C>,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, C)>,
+    H: FnMut(&S) -> C,
+    G: FnMut(&S) -> bool,
+{
+    let mut dist = HashMap::new();
+    let mut pred = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for start in starts {
+        let zero = C::default();
+        dist.insert(start.clone(), zero);
+        heap.push(Entry {
+            priority: heuristic(&start),
+            cost: zero,
+            state: start,
+        });
+    }
+    while let Some(Entry { cost, state, .. }) = heap.pop() {
+        if dist.get(&state).is_some_and(|&best| cost > best) {
+            continue;
+        }
+        if is_goal(&state) {
+            return SearchResult {
+                dist,
+                pred,
+                goal: Some(state),
+            };
+        }
+        for (next, step) in neighbours(&state) {
+            let next_cost = cost + step;
+            if dist.get(&next).is_some_and(|&best| next_cost >= best) {
+                continue;
+            }
+            dist.insert(next.clone(), next_cost);
+            pred.insert(next.clone(), state.clone());
+            heap.push(Entry {
+                priority: next_cost + heuristic(&next),
+                cost: next_cost,
+                state: next,
+            });
+        }
+    }
+    SearchResult {
+        dist,
+        pred,
+        goal: None,
+    }
+}
+
+/// Breadth-first search where every edge costs one step.
+pub fn bfs<S, N, I, G>(
+    starts: impl IntoIterator<Item = S>,
+    mut neighbours: N,
+    mut is_goal: G,
+) -> SearchResult<S, usize>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = S>,
+    G: FnMut(&S) -> bool,
+{
+    let mut dist = HashMap::new();
+    let mut pred = HashMap::new();
+    let mut queue = VecDeque::new();
+    for start in starts {
+        if dist.insert(start.clone(), 0).is_none() {
+            queue.push_back(start);
+        }
+    }
+    while let Some(state) = queue.pop_front() {
+        if is_goal(&state) {
+            return SearchResult {
+                dist,
+                pred,
+                goal: Some(state),
+            };
+        }
+        let d = dist[&state];
+        for next in neighbours(&state) {
+            if dist.contains_key(&next) {
+                continue;
+            }
+            dist.insert(next.clone(), d + 1);
+            pred.insert(next.clone(), state.clone());
+            queue.push_back(next);
+        }
+    }
+    SearchResult {
+        dist,
+        pred,
+        goal: None,
+    }
+}
+
+/// 0-1 BFS: like `bfs` but neighbours also say whether the edge is free (`0`) or costs one step (`1`).
+pub fn bfs_01<S, N, I, G>(
+    starts: impl IntoIterator<Item = S>,
+    mut neighbours: N,
+    mut is_goal: G,
+) -> SearchResult<S, usize>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    G: FnMut(&S) -> bool,
+{
+    let mut dist = HashMap::new();
+    let mut pred = HashMap::new();
+    let mut queue = VecDeque::new();
+    for start in starts {
+        dist.insert(start.clone(), 0);
+        queue.push_back((0, start));
+    }
+    while let Some((d, state)) = queue.pop_front() {
+        if dist.get(&state).is_some_and(|&best| d > best) {
+            continue;
+        }
+        if is_goal(&state) {
+            return SearchResult {
+                dist,
+                pred,
+                goal: Some(state),
+            };
+        }
+        for (next, weight) in neighbours(&state) {
+            assert!(weight <= 1, "0-1 BFS edge weights must be 0 or 1");
+            let nd = d + weight;
+            if dist.get(&next).is_some_and(|&best| nd >= best) {
+                continue;
+            }
+            dist.insert(next.clone(), nd);
+            pred.insert(next.clone(), state.clone());
+            if weight == 0 {
+                queue.push_front((nd, next));
+            } else {
+                queue.push_back((nd, next));
+            }
+        }
+    }
+    SearchResult {
+        dist,
+        pred,
+        goal: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "..#.
+..#.
+....
+#...";
+
+    fn open_neighbours(grid: &[Vec<char>], (r, c): (usize, usize)) -> Vec<(usize, usize)> {
+        let (rows, cols) = (grid.len() as isize, grid[0].len() as isize);
+        [(1, 0), (0, -1), (-1, 0), (0, 1)]
+            .into_iter()
+            .map(|(dr, dc)| (r as isize + dr, c as isize + dc))
+            .filter(|&(nr, nc)| nr >= 0 && nr < rows && nc >= 0 && nc < cols)
+            .map(|(nr, nc)| (nr as usize, nc as usize))
+            .filter(|&(nr, nc)| grid[nr][nc] != '#')
+            .collect()
+    }
+
+    #[test]
+    fn check_search_agree_on_grid() {
+        let grid = GRID
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect::<Vec<Vec<char>>>();
+        let goal = (0, 3);
+        let by_bfs = bfs([(0, 0)], |&p| open_neighbours(&grid, p), |&p| p == goal);
+        let by_dijkstra = dijkstra(
+            [(0, 0)],
+            |&p| open_neighbours(&grid, p).into_iter().map(|n| (n, 1)),
+            |&p| p == goal,
+        );
+        let by_astar = astar(
+            [(0, 0)],
+            |&p| open_neighbours(&grid, p).into_iter().map(|n| (n, 1)),
+            |&(r, c): &(usize, usize)| r.abs_diff(goal.0) + c.abs_diff(goal.1),
+            |&p| p == goal,
+        );
+        assert_eq!(by_bfs.goal_distance(), Some(7));
+        assert_eq!(by_dijkstra.goal_distance(), Some(7));
+        assert_eq!(by_astar.goal_distance(), Some(7));
+        let path = by_astar.goal_path().unwrap();
+        assert_eq!(path.len(), 8);
+        assert_eq!(path.first(), Some(&(0, 0)));
+        assert_eq!(path.last(), Some(&goal));
+    }
+
+    #[test]
+    fn check_search_multi_source_and_01() {
+        // walking right costs 1, jumping two to the right is free
+        let result = bfs_01(
+            [0, 1],
+            |&x: &i32| {
+                if x >= 10 {
+                    vec![]
+                } else {
+                    vec![(x + 1, 1), (x + 2, 0)]
+                }
+            },
+            |_| false,
+        );
+        assert_eq!(result.distance(&10), Some(0));
+        assert_eq!(result.distance(&7), Some(0));
+        assert_eq!(result.path_to(&7), Some(vec![1, 3, 5, 7]));
+        assert_eq!(result.goal, None);
+    }
+}
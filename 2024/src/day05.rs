@@ -1,11 +1,25 @@
+use crate::parse::{self, ParseError, Span};
 use crate::Solution;
 use itertools::iproduct;
-use itertools::Itertools;
 use std::collections::HashSet;
 
+type Manual = (HashSet<(u32, u32)>, Vec<Vec<u32>>);
+
 pub struct Day05;
 
 impl Day05 {
+    fn parse_manual(input: Span) -> Result<Manual, ParseError> {
+        let blocks = input.blocks();
+        let [rules, updates] = blocks[..] else {
+            return Err(input.error("expected rules and updates separated by a blank line"));
+        };
+        let rules = parse::lines(rules, |line| {
+            parse::tuple::<u32, 2>(line, "|").map(|[a, b]| (a, b))
+        })?;
+        let updates = parse::lines(updates, |line| parse::list(line, ","))?;
+        Ok((rules.into_iter().collect(), updates))
+    }
+
     fn correctly_ordered(update: &Vec<u32>, rules: &HashSet<(u32, u32)>) -> bool {
         iproduct!(update.iter().enumerate(), update.iter().enumerate())
             .filter(|((i, &a), (j, &b))| i < j && rules.contains(&(b, a)))
@@ -46,24 +60,10 @@ impl Day05 {
 }
 
 impl Solution for Day05 {
-    type ParsedInput = (HashSet<(u32, u32)>, Vec<Vec<u32>>);
+    type ParsedInput = Manual;
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        let (rules_str, updates_str) = input_lines.split_once("\n\n").unwrap();
-        let rules = rules_str
-            .lines()
-            .map(|line| {
-                line.split("|")
-                    .map(|s| s.parse::<u32>().unwrap())
-                    .collect_tuple()
-                    .unwrap()
-            })
-            .collect();
-        let updates = updates_str
-            .lines()
-            .map(|line| line.split(",").map(|s| s.parse::<u32>().unwrap()).collect())
-            .collect();
-        (rules, updates)
+        Self::parse_manual(Span::new(input_lines)).unwrap_or_else(|e| panic!("Day 5: {}", e))
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
@@ -6,6 +6,8 @@ pub mod day05;
 pub mod day06;
 pub mod day07;
 
+pub use common::parse;
+
 pub trait Solution {
     type ParsedInput;
     /// Parse the input into the type used by the solution.
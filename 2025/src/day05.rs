@@ -1,79 +1,39 @@
+use crate::intervals::{Interval, IntervalSet};
+use crate::parse::{self, ParseError, Span};
 use crate::Solution;
-use itertools::Itertools;
-use std::cmp;
-use std::collections::VecDeque;
 
 pub struct Day05;
 
 impl Day05 {
-    fn fresh_ids(ranges: &Vec<(u64, u64)>, ids: &Vec<u64>) -> Vec<u64> {
-        let mut result = Vec::new();
-        for &id in ids.iter() {
-            for &(a, b) in ranges.iter() {
-                if a <= id && id <= b {
-                    result.push(id);
-                    break;
-                }
+    fn parse_database(input: Span) -> Result<(IntervalSet<u64>, Vec<u64>), ParseError> {
+        let blocks = input.blocks();
+        let [ranges, ids] = blocks[..] else {
+            return Err(input.error("expected ranges and ids separated by a blank line"));
+        };
+        let ranges = parse::lines(ranges, |line| {
+            let [lo, hi] = parse::tuple::<u64, 2>(line, "-")?;
+            if lo > hi {
+                return Err(line.error(format!("range {}-{} is backwards", lo, hi)));
             }
-        }
-        result
+            Ok(Interval::new(lo, hi))
+        })?;
+        let ids = parse::lines(ids, |line| line.trim().parse())?;
+        Ok((ranges.into_iter().collect(), ids))
     }
 
-    fn overlapping(u: (u64, u64), v: (u64, u64)) -> bool {
-        cmp::max(u.0, v.0) <= cmp::min(u.1, v.1)
-    }
-
-    fn merge(u: (u64, u64), v: (u64, u64)) -> Option<(u64, u64)> {
-        if Self::overlapping(u, v) {
-            Some((cmp::min(u.0, v.0), cmp::max(u.1, v.1)))
-        } else {
-            None
-        }
-    }
-
-    fn merge_overlapping(ranges: &Vec<(u64, u64)>) -> Vec<(u64, u64)> {
-        let mut queue = VecDeque::<(u64, u64)>::from(ranges.clone());
-        let mut result = Vec::new();
-        while let Some(mut u) = queue.pop_front() {
-            let mut merges = 0;
-            for _ in 0..queue.len() {
-                let v = queue.pop_front().unwrap();
-                if let Some(uv) = Self::merge(u, v) {
-                    u = uv;
-                    merges += 1;
-                } else {
-                    queue.push_back(v);
-                }
-            }
-            if merges > 0 {
-                queue.push_back(u);
-            } else {
-                result.push(u);
-            }
-        }
-        result
+    fn fresh_ids(ranges: &IntervalSet<u64>, ids: &[u64]) -> Vec<u64> {
+        ids.iter()
+            .copied()
+            .filter(|&id| ranges.contains(id))
+            .collect()
     }
 }
 
 impl Solution for Day05 {
-    type ParsedInput = (Vec<(u64, u64)>, Vec<u64>);
+    type ParsedInput = (IntervalSet<u64>, Vec<u64>);
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        let (ranges_str, ids_str) = input_lines.split_once("\n\n").unwrap();
-        let ranges = ranges_str
-            .lines()
-            .map(|line| {
-                line.split("-")
-                    .map(|s| s.parse::<u64>().unwrap())
-                    .collect_tuple()
-                    .unwrap()
-            })
-            .collect();
-        let ids = ids_str
-            .lines()
-            .map(|line| line.parse::<u64>().unwrap())
-            .collect();
-        (ranges, ids)
+        Self::parse_database(Span::new(input_lines)).unwrap_or_else(|e| panic!("Day 5: {}", e))
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
@@ -83,11 +43,7 @@ impl Solution for Day05 {
 
     fn part_two(_parsed_input: &mut Self::ParsedInput) -> String {
         let (ranges, _) = _parsed_input;
-        Self::merge_overlapping(&ranges)
-            .iter()
-            .map(|&(a, b)| b - a + 1)
-            .sum::<u64>()
-            .to_string()
+        ranges.len().to_string()
     }
 }
 
@@ -1,7 +1,7 @@
+use crate::parse::{self, Span};
+use crate::unionfind::UnionFind;
 use crate::Solution;
 use itertools::Itertools;
-use std::cmp;
-use std::collections::HashMap;
 
 pub struct Day08;
 
@@ -13,72 +13,32 @@ impl Day08 {
         Vec<Vec<(usize, usize, usize)>>,
         Vec<((usize, usize, usize), (usize, usize, usize))>,
     ) {
-        let mut circuits: Vec<Vec<(usize, usize, usize)>> = junctions
-            .iter()
-            .map(|&junction| Vec::from([junction]))
-            .collect();
-        let mut junction_to_circuit: HashMap<(usize, usize, usize), usize> = HashMap::new();
-        for (i, circuit) in circuits.iter().enumerate() {
-            for &junction in circuit.iter() {
-                junction_to_circuit.insert(junction, i);
-            }
-        }
-        // println!("junction_to_circuit {:?}", junction_to_circuit);
-        let mut pairs: Vec<(usize, (usize, usize, usize), (usize, usize, usize))> = Vec::new();
-        for (&u, &v) in junctions.iter().tuple_combinations() {
+        let mut pairs: Vec<(usize, usize, usize)> = Vec::new();
+        for ((i, &u), (j, &v)) in junctions.iter().enumerate().tuple_combinations() {
             // distance squared
             let d = (((u.0 as isize - v.0 as isize) * (u.0 as isize - v.0 as isize))
                 + ((u.1 as isize - v.1 as isize) * (u.1 as isize - v.1 as isize))
                 + ((u.2 as isize - v.2 as isize) * (u.2 as isize - v.2 as isize)))
                 as usize;
-            pairs.push((d, u, v));
+            pairs.push((d, i, j));
         }
-        pairs.sort();
-        pairs.reverse();
+        pairs.sort_by_key(|&(d, i, j)| (d, junctions[i], junctions[j]));
+        let mut circuits = UnionFind::new(junctions.len());
         let mut log = Vec::new();
-        let mut total_circuits = circuits.len();
-        loop {
-            if limit.is_some() && log.len() >= limit.unwrap() {
+        for (_, i, j) in pairs {
+            if limit.is_some_and(|limit| log.len() >= limit) {
                 break;
             }
-            if limit.is_none() && total_circuits == 1 {
+            if limit.is_none() && circuits.count() == 1 {
                 break;
             }
-            let (_, u, v) = pairs.pop().unwrap();
-            let i = junction_to_circuit[&u];
-            let j = junction_to_circuit[&v];
-            // println!("-------------------------------");
-            // println!("connecting {:?} and {:?}", u, v);
-            log.push((u, v));
-            if i == j {
-                continue;
-            }
-            let (dst, src) = (cmp::min(i, j), cmp::max(i, j));
-            // println!("before merge:");
-            // println!("circuits[dst]: {:?}", circuits[dst]);
-            // println!("circuits[src]: {:?}", circuits[src]);
-            let mut merged_circuit = circuits[dst].clone();
-            merged_circuit.extend(circuits[src].clone());
-            circuits[dst] = merged_circuit;
-            circuits[src].clear();
-            // println!("after merge:");
-            // println!("{:?}", circuits[dst]);
-            // println!("size: {:?}", circuits[dst].len());
-            // println!("{:?}", circuits.iter().map(|c| c.len()).collect::<Vec<_>>());
-            for junction in circuits[dst].iter() {
-                junction_to_circuit.insert(*junction, dst);
-            }
-            total_circuits -= 1;
+            log.push((junctions[i], junctions[j]));
+            circuits.union(i, j);
         }
-        // println!("-------------------------------");
-        circuits = circuits
-            .into_iter()
-            .filter(|circuit| circuit.len() != 0)
+        let circuits = circuits
+            .components()
+            .map(|component| component.into_iter().map(|i| junctions[i]).collect())
             .collect();
-        // for circuit in circuits.iter() {
-        // println!("{:?}", circuit);
-        // }
-        // println!("{:?} circuits", circuits.len());
         (circuits, log)
     }
 }
@@ -87,15 +47,10 @@ impl Solution for Day08 {
     type ParsedInput = Vec<(usize, usize, usize)>;
 
     fn parse_input(input_lines: &str) -> Self::ParsedInput {
-        input_lines
-            .lines()
-            .map(|line| {
-                line.split(",")
-                    .map(|s| s.parse::<usize>().unwrap())
-                    .collect_tuple()
-                    .unwrap()
-            })
-            .collect()
+        parse::lines(Span::new(input_lines), |line| {
+            parse::tuple::<usize, 3>(line, ",").map(|[x, y, z]| (x, y, z))
+        })
+        .unwrap_or_else(|e| panic!("Day 8: {}", e))
     }
 
     fn part_one(_parsed_input: &mut Self::ParsedInput) -> String {
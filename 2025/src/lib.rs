@@ -10,6 +10,9 @@ pub mod day09;
 // pub mod day10;
 // pub mod day11;
 // pub mod day12;
+pub mod unionfind;
+
+pub use common::{intervals, parse, polygon};
 
 pub trait Solution {
     type ParsedInput;
@@ -0,0 +1,117 @@
+/// Disjoint sets over `0..n` with path compression and union by size.
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    count: usize,
+}
+
+impl UnionFind {
+    pub fn new(n: usize) -> Self {
+        UnionFind {
+            parent: (0..n).collect(),
+            size: vec![1; n],
+            count: n,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    /// Number of disjoint components.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut x = x;
+        while self.parent[x] != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
+        }
+        root
+    }
+
+    /// Merge the components of `a` and `b`, returning false if they were already joined.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut ra, mut rb) = (self.find(a), self.find(b));
+        if ra == rb {
+            return false;
+        }
+        if self.size[ra] < self.size[rb] {
+            std::mem::swap(&mut ra, &mut rb);
+        }
+        self.parent[rb] = ra;
+        self.size[ra] += self.size[rb];
+        self.count -= 1;
+        true
+    }
+
+    pub fn connected(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    /// Size of the component containing `x`.
+    pub fn size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    /// Sizes of every component, one entry per component.
+    pub fn sizes(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&x| self.parent[x] == x)
+            .map(|x| self.size[x])
+            .collect()
+    }
+
+    /// Every component as a list of its members, ordered by smallest member.
+    pub fn components(&mut self) -> impl Iterator<Item = Vec<usize>> {
+        let mut by_root = vec![Vec::new(); self.len()];
+        for x in 0..self.len() {
+            let root = self.find(x);
+            by_root[root].push(x);
+        }
+        let mut components = by_root
+            .into_iter()
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        components.sort_by_key(|c| c[0]);
+        components.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_unionfind() {
+        let mut sets = UnionFind::new(6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(4, 5));
+        assert!(sets.union(1, 5));
+        assert!(!sets.union(0, 4));
+        assert!(sets.connected(0, 5));
+        assert!(!sets.connected(2, 3));
+        assert_eq!(sets.count(), 3);
+        assert_eq!(sets.size(4), 4);
+        assert_eq!(
+            sets.components().collect::<Vec<_>>(),
+            vec![vec![0, 1, 4, 5], vec![2], vec![3]]
+        );
+        let mut sizes = sets.sizes();
+        sizes.sort();
+        assert_eq!(sizes, vec![1, 1, 4]);
+    }
+}
@@ -0,0 +1,422 @@
+use std::fmt::{self, Debug, Display};
+use std::ops::{Add, Sub};
+
+/// Integer types usable as interval bounds.
+pub trait Bound: Copy + Ord + Debug + Add<Output = Self> + Sub<Output = Self> {
+    const ZERO: Self;
+    const ONE: Self;
+    const MIN: Self;
+    const MAX: Self;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(impl Bound for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+        })*
+    };
+}
+
+impl_bound!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+/// A non-empty inclusive range `lo..=hi`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Bound> Interval<T> {
+    pub fn new(lo: T, hi: T) -> Self {
+        assert!(lo <= hi, "empty interval {:?}..={:?}", lo, hi);
+        Interval { lo, hi }
+    }
+
+    /// The interval `start..start + len`, or `None` if `len` is zero.
+    pub fn from_start_len(start: T, len: T) -> Option<Self> {
+        if len == T::ZERO {
+            None
+        } else {
+            Some(Interval::new(start, start + (len - T::ONE)))
+        }
+    }
+
+    /// Number of points in the interval. Overflows for the full range of `T`.
+    pub fn len(&self) -> T {
+        self.hi - self.lo + T::ONE
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        self.lo <= x && x <= self.hi
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let (lo, hi) = (self.lo.max(other.lo), self.hi.min(other.hi));
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+
+    /// Split into the parts strictly below `mid` and at or above `mid`.
+    pub fn split_at(&self, mid: T) -> (Option<Self>, Option<Self>) {
+        if mid <= self.lo {
+            (None, Some(*self))
+        } else if mid > self.hi {
+            (Some(*self), None)
+        } else {
+            (
+                Some(Interval::new(self.lo, mid - T::ONE)),
+                Some(Interval::new(mid, self.hi)),
+            )
+        }
+    }
+
+    /// Move the interval so that it starts at `lo`.
+    pub fn moved_to(&self, lo: T) -> Self {
+        Interval::new(lo, lo + (self.hi - self.lo))
+    }
+}
+
+/// A set of integers stored as sorted, disjoint, non-adjacent intervals.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Bound> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet::new()
+    }
+}
+
+impl<T: Bound> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut intervals = iter.into_iter().collect::<Vec<_>>();
+        intervals.sort();
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if last.hi == T::MAX || interval.lo <= last.hi + T::ONE => {
+                    last.hi = last.hi.max(interval.hi);
+                }
+                _ => merged.push(interval),
+            }
+        }
+        IntervalSet { intervals: merged }
+    }
+}
+
+impl<T: Bound> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Total number of points in the set.
+    pub fn len(&self) -> T {
+        self.intervals
+            .iter()
+            .fold(T::ZERO, |total, interval| total + interval.len())
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.intervals.first().map(|interval| interval.lo)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.intervals.last().map(|interval| interval.hi)
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        let i = self.intervals.partition_point(|interval| interval.hi < x);
+        self.intervals
+            .get(i)
+            .is_some_and(|interval| interval.lo <= x)
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        *self = self.union(&IntervalSet::from_iter([interval]));
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.iter().chain(other.iter()).copied().collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let (mut i, mut j) = (0, 0);
+        let mut result = Vec::new();
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            if let Some(both) = a.intersect(&b) {
+                result.push(both);
+            }
+            if a.hi < b.hi {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals: result }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut result = Vec::new();
+        let mut j = 0;
+        for &a in self.intervals.iter() {
+            let mut rest = Some(a);
+            while j < other.intervals.len() && other.intervals[j].hi < a.lo {
+                j += 1;
+            }
+            let mut k = j;
+            while let (Some(r), Some(b)) = (rest, other.intervals.get(k)) {
+                if b.lo > r.hi {
+                    break;
+                }
+                let (below, _) = r.split_at(b.lo);
+                if let Some(below) = below {
+                    result.push(below);
+                }
+                rest = if b.hi >= r.hi {
+                    None
+                } else {
+                    r.split_at(b.hi + T::ONE).1
+                };
+                k += 1;
+            }
+            if let Some(r) = rest {
+                result.push(r);
+            }
+        }
+        IntervalSet { intervals: result }
+    }
+}
+
+/// A piecewise translation: each source interval is moved to start at its destination,
+/// and points outside every source are left unchanged.
+/// Where sources overlap, the piece listed first wins.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OffsetMap<T> {
+    pieces: Vec<(Interval<T>, T)>,
+}
+
+impl<T: Bound> FromIterator<(Interval<T>, T)> for OffsetMap<T> {
+    fn from_iter<I: IntoIterator<Item = (Interval<T>, T)>>(iter: I) -> Self {
+        OffsetMap {
+            pieces: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Bound> OffsetMap<T> {
+    /// The `(source, destination start)` pieces in priority order.
+    pub fn pieces(&self) -> &[(Interval<T>, T)] {
+        &self.pieces
+    }
+
+    pub fn map_point(&self, x: T) -> T {
+        for &(source, dst) in self.pieces.iter() {
+            if source.contains(x) {
+                return dst + (x - source.lo);
+            }
+        }
+        x
+    }
+
+    pub fn map_set(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut unmapped = set.clone();
+        let mut mapped = Vec::new();
+        for &(source, dst) in self.pieces.iter() {
+            let source_set = IntervalSet::from_iter([source]);
+            for hit in unmapped.intersection(&source_set).iter() {
+                mapped.push(hit.moved_to(dst + (hit.lo - source.lo)));
+            }
+            unmapped = unmapped.difference(&source_set);
+        }
+        mapped.extend(unmapped.intervals);
+        mapped.into_iter().collect()
+    }
+
+    /// The pieces as they actually apply: with the parts hidden by earlier pieces cut away
+    /// and the stretches left unchanged filled in, so they cover all of `T` in order.
+    pub fn normalized(&self) -> Vec<(Interval<T>, T)> {
+        let mut taken = IntervalSet::new();
+        let mut pieces = Vec::new();
+        for &(source, dst) in self.pieces.iter() {
+            let visible = IntervalSet::from_iter([source]).difference(&taken);
+            pieces.extend(
+                visible
+                    .iter()
+                    .map(|part| (*part, dst + (part.lo - source.lo))),
+            );
+            taken.insert(source);
+        }
+        let untouched = IntervalSet::from_iter([Interval::new(T::MIN, T::MAX)]).difference(&taken);
+        pieces.extend(untouched.iter().map(|gap| (*gap, gap.lo)));
+        pieces.sort();
+        pieces
+    }
+
+    /// Build a map from sorted, disjoint pieces, joining neighbours that move by the
+    /// same amount and leaving out the ones that don't move at all.
+    fn from_normalized(pieces: Vec<(Interval<T>, T)>) -> Self {
+        let mut joined: Vec<(Interval<T>, T)> = Vec::new();
+        for (source, dst) in pieces {
+            if let Some((last, last_dst)) = joined.last_mut() {
+                // compared this way round so that nothing overflows near the top of `T`
+                let same_move = dst >= *last_dst && dst - *last_dst == source.lo - last.lo;
+                if last.hi + T::ONE == source.lo && same_move {
+                    last.hi = source.hi;
+                    continue;
+                }
+            }
+            joined.push((source, dst));
+        }
+        joined.retain(|&(source, dst)| source.lo != dst);
+        OffsetMap { pieces: joined }
+    }
+
+    /// The single map that applies `self` and then `then`.
+    pub fn compose(&self, then: &OffsetMap<T>) -> OffsetMap<T> {
+        let after = then.normalized();
+        let mut pieces = Vec::new();
+        for (source, dst) in self.normalized() {
+            let image = source.moved_to(dst);
+            for &(next_source, next_dst) in after.iter() {
+                if let Some(part) = image.intersect(&next_source) {
+                    let from = part.moved_to(source.lo + (part.lo - dst));
+                    pieces.push((from, next_dst + (part.lo - next_source.lo)));
+                }
+            }
+        }
+        pieces.sort();
+        Self::from_normalized(pieces)
+    }
+
+    /// Every point that the map sends into `set`.
+    pub fn preimage(&self, set: &IntervalSet<T>) -> IntervalSet<T> {
+        self.normalized()
+            .into_iter()
+            .flat_map(|(source, dst)| {
+                let image = IntervalSet::from_iter([source.moved_to(dst)]);
+                set.intersection(&image)
+                    .iter()
+                    .map(|hit| hit.moved_to(source.lo + (hit.lo - dst)))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+impl<T: Bound + Display> Display for OffsetMap<T> {
+    /// One line per piece, like `79..=92 -> 81..=94`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (source, dst) in self.pieces.iter() {
+            let image = source.moved_to(*dst);
+            writeln!(
+                f,
+                "{}..={} -> {}..={}",
+                source.lo, source.hi, image.lo, image.hi
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set<T: Bound>(ranges: &[(T, T)]) -> IntervalSet<T> {
+        ranges
+            .iter()
+            .map(|&(lo, hi)| Interval::new(lo, hi))
+            .collect()
+    }
+
+    #[test]
+    fn check_interval_set_operations() {
+        let a = set::<u64>(&[(3, 5), (10, 14), (16, 20), (12, 18), (21, 21)]);
+        assert_eq!(a, set(&[(3, 5), (10, 21)]));
+        assert_eq!(a.len(), 15);
+        assert!(a.contains(21) && !a.contains(9) && !a.contains(22));
+        let b = set::<u64>(&[(0, 3), (5, 11), (20, 30)]);
+        assert_eq!(a.union(&b), set(&[(0, 30)]));
+        assert_eq!(
+            a.intersection(&b),
+            set(&[(3, 3), (5, 5), (10, 11), (20, 21)])
+        );
+        assert_eq!(a.difference(&b), set(&[(4, 4), (12, 19)]));
+        assert_eq!(b.difference(&a), set(&[(0, 2), (6, 9), (22, 30)]));
+    }
+
+    #[test]
+    fn check_interval_set_extremes() {
+        let full = set::<i128>(&[(i128::MIN, -1), (0, i128::MAX)]);
+        assert_eq!(full, set(&[(i128::MIN, i128::MAX)]));
+        let hole = full.difference(&set(&[(0, 0)]));
+        assert_eq!(hole, set(&[(i128::MIN, -1), (1, i128::MAX)]));
+        assert!(!hole.contains(0));
+        assert_eq!(
+            set::<u8>(&[(250, 255)]).difference(&set(&[(255, 255)])),
+            set(&[(250, 254)])
+        );
+    }
+
+    #[test]
+    fn check_offset_map() {
+        // the seed-to-soil map from 2023 day 5
+        let map = [(98, 50, 2), (50, 52, 48)]
+            .into_iter()
+            .map(|(src, dst, len)| (Interval::from_start_len(src, len).unwrap(), dst))
+            .collect::<OffsetMap<u32>>();
+        assert_eq!(map.map_point(79), 81);
+        assert_eq!(map.map_point(99), 51);
+        assert_eq!(map.map_point(13), 13);
+        assert_eq!(map.map_set(&set(&[(45, 60)])), set(&[(45, 49), (52, 62)]));
+        assert_eq!(map.map_set(&set(&[(96, 99)])), set(&[(50, 51), (98, 99)]));
+        assert_eq!(map.preimage(&set(&[(50, 52)])), set(&[(50, 50), (98, 99)]));
+        assert_eq!(map.preimage(&set(&[(98, 99)])), set(&[(96, 97)]));
+        assert_eq!(map.to_string(), "98..=99 -> 50..=51\n50..=97 -> 52..=99\n");
+    }
+
+    #[test]
+    fn check_offset_map_compose() {
+        let map = |pieces: &[(u64, u64, u64)]| {
+            pieces
+                .iter()
+                .map(|&(src, dst, len)| (Interval::from_start_len(src, len).unwrap(), dst))
+                .collect::<OffsetMap<u64>>()
+        };
+        // overlapping pieces, where the first one listed wins
+        let first = map(&[
+            (10, 100, 10),
+            (15, 0, 10),
+            (u64::MAX - 1, 5, 2),
+            (u64::MAX - 5, u64::MAX - 2, 2),
+        ]);
+        let second = map(&[(100, 10, 3), (0, 200, 50)]);
+        let composed = first.compose(&second);
+        for x in (0..300).chain(u64::MAX - 6..=u64::MAX) {
+            assert_eq!(
+                composed.map_point(x),
+                second.map_point(first.map_point(x)),
+                "{}",
+                x
+            );
+        }
+        // swapping two blocks back and forth is no move at all
+        let swap = map(&[(0, 10, 10), (10, 0, 10)]);
+        assert!(swap.compose(&swap).pieces().is_empty());
+        assert_eq!(first.normalized().first(), Some(&(Interval::new(0, 9), 0)));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod intervals;
+pub mod parse;
+pub mod polygon;
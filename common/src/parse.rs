@@ -0,0 +1,317 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Where and why the input couldn't be parsed. Lines and columns count from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A piece of the puzzle input that remembers where it starts,
+/// so that errors from parsing it can point at the right place.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a> Span<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Span {
+            text,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// The part of this span between the byte offsets `start` and `end`.
+    fn sub(&self, start: usize, end: usize) -> Span<'a> {
+        let before = &self.text[..start];
+        let (line, column) = match before.rfind('\n') {
+            Some(i) => (
+                self.line + before.matches('\n').count(),
+                before[i + 1..].chars().count() + 1,
+            ),
+            None => (self.line, self.column + before.chars().count()),
+        };
+        Span {
+            text: &self.text[start..end],
+            line,
+            column,
+        }
+    }
+
+    /// Wrap a slice of `self.text` (e.g. from a `str` method) as a span.
+    pub fn of(&self, inner: &'a str) -> Span<'a> {
+        let start = inner.as_ptr() as usize - self.text.as_ptr() as usize;
+        assert!(
+            start + inner.len() <= self.text.len(),
+            "not a slice of this span"
+        );
+        self.sub(start, start + inner.len())
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn trim(&self) -> Span<'a> {
+        self.of(self.text.trim())
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text.lines().map(|line| self.of(line))
+    }
+
+    /// Groups of lines separated by blank lines.
+    pub fn blocks(&self) -> Vec<Span<'a>> {
+        let mut blocks = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        for line in self.text.lines() {
+            let start = line.as_ptr() as usize - self.text.as_ptr() as usize;
+            if line.trim().is_empty() {
+                blocks.extend(current.take());
+            } else {
+                let (block_start, _) = current.unwrap_or((start, start));
+                current = Some((block_start, start + line.len()));
+            }
+        }
+        blocks.extend(current);
+        blocks
+            .into_iter()
+            .map(|(start, end)| self.sub(start, end))
+            .collect()
+    }
+
+    pub fn split<'b>(&'b self, separator: &'b str) -> impl Iterator<Item = Span<'a>> + 'b {
+        self.text.split(separator).map(|part| self.of(part))
+    }
+
+    pub fn split_whitespace(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text.split_whitespace().map(|part| self.of(part))
+    }
+
+    pub fn split_once(&self, separator: &str) -> Result<(Span<'a>, Span<'a>), ParseError> {
+        let (a, b) = self
+            .text
+            .split_once(separator)
+            .ok_or_else(|| self.error(format!("expected `{}` in `{}`", separator, self.text)))?;
+        Ok((self.of(a), self.of(b)))
+    }
+
+    pub fn strip_prefix(&self, prefix: &str) -> Result<Span<'a>, ParseError> {
+        self.text
+            .strip_prefix(prefix)
+            .map(|rest| self.of(rest))
+            .ok_or_else(|| self.error(format!("expected `{}`", prefix)))
+    }
+
+    pub fn strip_suffix(&self, suffix: &str) -> Result<Span<'a>, ParseError> {
+        self.text
+            .strip_suffix(suffix)
+            .map(|rest| self.of(rest))
+            .ok_or_else(|| {
+                let end = self.sub(self.text.len(), self.text.len());
+                end.error(format!("expected `{}`", suffix))
+            })
+    }
+
+    pub fn parse<T: FromStr>(&self) -> Result<T, ParseError> {
+        self.text.parse::<T>().map_err(|_| {
+            self.error(format!(
+                "couldn't parse `{}` as {}",
+                self.text,
+                std::any::type_name::<T>()
+            ))
+        })
+    }
+}
+
+/// Parse every line with `f`.
+pub fn lines<'a, T>(
+    span: Span<'a>,
+    f: impl FnMut(Span<'a>) -> Result<T, ParseError>,
+) -> Result<Vec<T>, ParseError> {
+    span.text.lines().map(|line| span.of(line)).map(f).collect()
+}
+
+/// Every (optionally negative) integer in the span, ignoring whatever is around them.
+/// A `-` straight after a digit separates a range like `2-4` rather than negating what follows.
+pub fn integers<T: FromStr>(span: Span) -> Result<Vec<T>, ParseError> {
+    let bytes = span.text.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let negative = bytes[i] == b'-'
+            && bytes.get(i + 1).is_some_and(u8::is_ascii_digit)
+            && !(i > 0 && bytes[i - 1].is_ascii_digit());
+        if !negative && !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i += 1;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            i += 1;
+        }
+        result.push(span.sub(start, i).parse()?);
+    }
+    Ok(result)
+}
+
+/// Values separated by `separator`, or by any whitespace if `separator` is blank.
+pub fn list<T: FromStr>(span: Span, separator: &str) -> Result<Vec<T>, ParseError> {
+    if separator.trim().is_empty() {
+        span.split_whitespace().map(|s| s.parse()).collect()
+    } else {
+        span.text
+            .split(separator)
+            .map(|s| span.of(s).trim().parse())
+            .collect()
+    }
+}
+
+/// Exactly `N` values separated by `separator`, like `3,4,5` or `10-14`.
+pub fn tuple<T: FromStr, const N: usize>(
+    span: Span,
+    separator: &str,
+) -> Result<[T; N], ParseError> {
+    let values = list::<T>(span, separator)?;
+    let found = values.len();
+    values.try_into().map_err(|_| {
+        span.error(format!(
+            "expected {} values separated by `{}`, found {}",
+            N, separator, found
+        ))
+    })
+}
+
+/// A line like `seeds: 79 14 55 13`, split into its key and whitespace-separated values.
+pub fn key_values<'a, T: FromStr>(
+    span: Span<'a>,
+    separator: &str,
+) -> Result<(Span<'a>, Vec<T>), ParseError> {
+    let (key, values) = span.split_once(separator)?;
+    Ok((key.trim(), list(values, " ")?))
+}
+
+/// A rectangular grid of characters.
+pub fn grid(span: Span) -> Result<Vec<Vec<char>>, ParseError> {
+    grid_with(span, Some)
+}
+
+/// A rectangular grid, converting each character with `f`.
+pub fn grid_with<T>(span: Span, f: impl Fn(char) -> Option<T>) -> Result<Vec<Vec<T>>, ParseError> {
+    let mut rows: Vec<Vec<T>> = Vec::new();
+    for line in span.lines() {
+        let mut row = Vec::new();
+        for (i, c) in line.text.char_indices() {
+            let cell = f(c).ok_or_else(|| {
+                line.sub(i, i + c.len_utf8())
+                    .error(format!("unexpected `{}`", c))
+            })?;
+            row.push(cell);
+        }
+        if let Some(first) = rows.first() {
+            if row.len() != first.len() {
+                return Err(line.error(format!(
+                    "expected a row of width {}, found {}",
+                    first.len(),
+                    row.len()
+                )));
+            }
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// A record like `px{a<2006:qkq,m>2090:A,rfg}` or `{x=787,m=2655}`,
+/// split into its (possibly empty) name and comma-separated items.
+pub fn record(span: Span) -> Result<(Span, Vec<Span>), ParseError> {
+    let (name, body) = span.trim().split_once("{")?;
+    let body = body.strip_suffix("}")?;
+    let items = if body.is_empty() {
+        Vec::new()
+    } else {
+        body.text.split(',').map(|item| body.of(item)).collect()
+    };
+    Ok((name, items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_parse_positions() {
+        let input = Span::new("seeds: 79 14\n\nmap:\n50 98 2\n52 x 48\n");
+        let blocks = input.blocks();
+        assert_eq!(blocks.len(), 2);
+        let (key, seeds) = key_values::<u32>(blocks[0], ":").unwrap();
+        assert_eq!((key.text, seeds), ("seeds", vec![79, 14]));
+        assert_eq!((blocks[1].line, blocks[1].column), (3, 1));
+        let rows = blocks[1].lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(tuple::<u32, 3>(rows[0], " ").unwrap(), [50, 98, 2]);
+        let error = tuple::<u32, 3>(rows[1], " ").unwrap_err();
+        assert_eq!((error.line, error.column), (5, 4));
+        assert_eq!(
+            error.to_string(),
+            "line 5, column 4: couldn't parse `x` as u32"
+        );
+        let error = tuple::<u32, 2>(rows[0], " ").unwrap_err();
+        assert_eq!((error.line, error.column), (4, 1));
+    }
+
+    #[test]
+    fn check_parse_helpers() {
+        let span = Span::new("p=-4,11 v=3,-3 -- 7-8");
+        assert_eq!(integers::<i64>(span).unwrap(), vec![-4, 11, 3, -3, 7, 8]);
+        assert_eq!(
+            integers::<i64>(Span::new("2-4,6-8")).unwrap(),
+            vec![2, 4, 6, 8]
+        );
+        assert_eq!(
+            integers::<u64>(Span::new("2-4,6-8")).unwrap(),
+            vec![2, 4, 6, 8]
+        );
+        assert_eq!(integers::<i64>(Span::new("-2--4")).unwrap(), vec![-2, -4]);
+        let (name, items) = record(Span::new("qqz{s>2770:qs,m<1801:hdj,R}")).unwrap();
+        assert_eq!(name.text, "qqz");
+        assert_eq!(
+            items.iter().map(|s| s.text).collect::<Vec<_>>(),
+            vec!["s>2770:qs", "m<1801:hdj", "R"]
+        );
+        assert_eq!(items[1].column, 15);
+        let error = record(Span::new("in{s<1351:px,qqz")).unwrap_err();
+        assert_eq!(error.column, 17);
+        assert_eq!(grid(Span::new("#.\n.#")).unwrap().len(), 2);
+        let error = grid(Span::new("#..\n.#\n")).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 1));
+        let error = grid_with(Span::new("12\n3a"), |c| c.to_digit(10)).unwrap_err();
+        assert_eq!((error.line, error.column), (2, 2));
+    }
+}
@@ -0,0 +1,223 @@
+/// A lattice point. Functions here work in either `(x, y)` or `(row, col)` coordinates,
+/// except that the two systems have opposite orientations.
+pub type Point = (isize, isize);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    /// Positive shoelace area: counterclockwise with `(x, y)`, clockwise with `(row, col)`.
+    Positive,
+    Negative,
+    Degenerate,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location {
+    Inside,
+    Boundary,
+    Outside,
+}
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn edges(vertices: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(&a, &b)| (a, b))
+}
+
+/// Twice the signed area, from the shoelace formula.
+/// https://en.wikipedia.org/wiki/Shoelace_formula
+pub fn twice_signed_area(vertices: &[Point]) -> isize {
+    edges(vertices)
+        .map(|((x, y), (nx, ny))| x * ny - nx * y)
+        .sum()
+}
+
+/// Area enclosed by the polygon, rounded down to a whole number.
+pub fn area(vertices: &[Point]) -> isize {
+    twice_signed_area(vertices).abs() / 2
+}
+
+pub fn orientation(vertices: &[Point]) -> Orientation {
+    match twice_signed_area(vertices).signum() {
+        1 => Orientation::Positive,
+        -1 => Orientation::Negative,
+        _ => Orientation::Degenerate,
+    }
+}
+
+/// Number of lattice points on the boundary.
+pub fn boundary_points(vertices: &[Point]) -> isize {
+    edges(vertices)
+        .map(|((x, y), (nx, ny))| gcd(nx - x, ny - y))
+        .sum()
+}
+
+/// Number of lattice points strictly inside, by Pick's theorem.
+/// https://en.wikipedia.org/wiki/Pick's_theorem
+pub fn interior_points(vertices: &[Point]) -> isize {
+    (twice_signed_area(vertices).abs() - boundary_points(vertices) + 2) / 2
+}
+
+fn on_segment((x, y): Point, (ax, ay): Point, (bx, by): Point) -> bool {
+    (bx - ax) * (y - ay) == (by - ay) * (x - ax)
+        && ax.min(bx) <= x
+        && x <= ax.max(bx)
+        && ay.min(by) <= y
+        && y <= ay.max(by)
+}
+
+/// Locate `p` with every vertex scaled by `scale`, so that callers can test half-integer points.
+fn locate_scaled(vertices: &[Point], p: Point, scale: isize) -> Location {
+    let (x, y) = p;
+    let mut inside = false;
+    for ((ax, ay), (bx, by)) in edges(vertices) {
+        let (a, b) = ((ax * scale, ay * scale), (bx * scale, by * scale));
+        if on_segment(p, a, b) {
+            return Location::Boundary;
+        }
+        // crossing number: count edges crossing the ray from p towards +x
+        if (a.1 > y) != (b.1 > y) {
+            let cross = (b.0 - a.0) * (y - a.1) - (x - a.0) * (b.1 - a.1);
+            if (cross > 0) == (b.1 > a.1) {
+                inside = !inside;
+            }
+        }
+    }
+    if inside {
+        Location::Inside
+    } else {
+        Location::Outside
+    }
+}
+
+/// Whether `p` is inside, on the boundary of, or outside a simple polygon.
+pub fn locate(vertices: &[Point], p: Point) -> Location {
+    locate_scaled(vertices, p, 1)
+}
+
+fn covers_segment(vertices: &[Point], a: Point, b: Point) -> bool {
+    // a segment can only leave the polygon between two consecutive vertex coordinates,
+    // so it is enough to check the ends and the midpoint of every such piece
+    let horizontal = a.1 == b.1;
+    let axis = |p: Point| if horizontal { p.0 } else { p.1 };
+    let (lo, hi) = (axis(a).min(axis(b)), axis(a).max(axis(b)));
+    let mut stops = vertices
+        .iter()
+        .map(|&v| axis(v))
+        .filter(|&t| lo < t && t < hi)
+        .collect::<Vec<_>>();
+    stops.extend([lo, hi]);
+    stops.sort();
+    stops.dedup();
+    let at = |t: isize| {
+        if horizontal {
+            (t, 2 * a.1)
+        } else {
+            (2 * a.0, t)
+        }
+    };
+    stops
+        .iter()
+        .map(|&t| at(2 * t))
+        .chain(stops.windows(2).map(|w| at(w[0] + w[1])))
+        .all(|p| locate_scaled(vertices, p, 2) != Location::Outside)
+}
+
+/// Whether the axis-aligned rectangle with opposite corners `a` and `b`
+/// lies entirely inside or on the boundary of a rectilinear polygon.
+pub fn contains_rectangle(vertices: &[Point], a: Point, b: Point) -> bool {
+    let (x1, x2) = (a.0.min(b.0), a.0.max(b.0));
+    let (y1, y2) = (a.1.min(b.1), a.1.max(b.1));
+    if x1 == x2 || y1 == y2 {
+        return covers_segment(vertices, (x1, y1), (x2, y2));
+    }
+    // with no edge cutting through its interior, the rectangle is all in or all out
+    for ((ax, ay), (bx, by)) in edges(vertices) {
+        assert!(ax == bx || ay == by, "polygon is not rectilinear");
+        if ax == bx {
+            if x1 < ax && ax < x2 && ay.min(by) < y2 && ay.max(by) > y1 {
+                return false;
+            }
+        } else if y1 < ay && ay < y2 && ax.min(bx) < x2 && ax.max(bx) > x1 {
+            return false;
+        }
+    }
+    locate_scaled(vertices, (x1 + x2, y1 + y2), 2) != Location::Outside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the red tiles from 2025 day 9
+    const TILES: [Point; 8] = [
+        (7, 1),
+        (11, 1),
+        (11, 7),
+        (9, 7),
+        (9, 5),
+        (2, 5),
+        (2, 3),
+        (7, 3),
+    ];
+
+    #[test]
+    fn check_polygon_measures() {
+        assert_eq!(twice_signed_area(&TILES), 2 * 30);
+        assert_eq!(orientation(&TILES), Orientation::Positive);
+        let reversed = TILES.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(orientation(&reversed), Orientation::Negative);
+        assert_eq!(area(&reversed), 30);
+        assert_eq!(boundary_points(&TILES), 30);
+        // all tiles = interior + boundary = 46 green and red tiles
+        assert_eq!(interior_points(&TILES), 16);
+        let triangle = [(0, 0), (4, 0), (0, 3)];
+        assert_eq!(area(&triangle), 6);
+        assert_eq!(boundary_points(&triangle), 8);
+        assert_eq!(interior_points(&triangle), 3);
+    }
+
+    #[test]
+    fn check_polygon_locate() {
+        assert_eq!(locate(&TILES, (8, 4)), Location::Inside);
+        assert_eq!(locate(&TILES, (2, 4)), Location::Boundary);
+        assert_eq!(locate(&TILES, (11, 1)), Location::Boundary);
+        assert_eq!(locate(&TILES, (3, 6)), Location::Outside);
+        assert_eq!(locate(&TILES, (12, 3)), Location::Outside);
+        assert_eq!(locate(&TILES, (3, 1)), Location::Outside);
+    }
+
+    #[test]
+    fn check_polygon_contains_rectangle() {
+        assert!(contains_rectangle(&TILES, (9, 5), (2, 3)));
+        assert!(!contains_rectangle(&TILES, (7, 1), (11, 7)));
+        assert!(contains_rectangle(&TILES, (7, 3), (11, 1)));
+        assert!(contains_rectangle(&TILES, (2, 3), (11, 3)));
+        assert!(!contains_rectangle(&TILES, (7, 1), (7, 7)));
+        assert!(contains_rectangle(&TILES, (2, 5), (9, 5)));
+        assert!(contains_rectangle(&TILES, (9, 7), (9, 7)));
+        // a U shape, the rectangle bridges the gap
+        let u = [
+            (0, 0),
+            (6, 0),
+            (6, 4),
+            (4, 4),
+            (4, 2),
+            (2, 2),
+            (2, 4),
+            (0, 4),
+        ];
+        assert!(contains_rectangle(&u, (0, 0), (6, 2)));
+        assert!(!contains_rectangle(&u, (0, 0), (6, 4)));
+        assert!(!contains_rectangle(&u, (0, 4), (6, 4)));
+        assert!(contains_rectangle(&u, (0, 2), (6, 2)));
+    }
+}